@@ -1,17 +1,10 @@
+use std::time::Duration;
+
 use replman::prelude::*;
 
 #[derive(PartialEq, Debug, ReplCmd)]
 #[replman(rename_all = "snake_case")]
 pub enum Command {
-    /// Displays help
-    #[replman(alias = "h")]
-    Help,
-    /// Exits the program
-    ///
-    /// make sure to use it
-    #[replman(alias = "exit")]
-    #[replman(starts_with = "q")]
-    Quit,
     Quote,
     NamedArg {
         left: usize,
@@ -30,23 +23,45 @@ pub enum Command {
         #[replman(default = "42")]
         with_default_explicit: u32,
     },
+    /// Logs a message in the background after a delay
+    Remind {
+        seconds: u64,
+        message: String,
+    },
 }
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
-    let mut repl = Repl::new();
-    loop {
-        let command: Command = repl.read_command()?;
+    let mut builder = Repl::builder().prompt("example> ");
+
+    // `example_repl --script commands.repl` runs the commands in the file
+    let args: Vec<_> = std::env::args().skip(1).collect();
+    if let [flag, path] = args.as_slice() {
+        if flag == "--script" {
+            builder = builder.script_file(path).echo(true);
+        }
+    }
 
+    let mut repl = builder.build()?;
+
+    // Log messages from background tasks don't mess up the prompt
+    tracing_subscriber::fmt().with_writer(repl.printer()).init();
+
+    repl.run_async(&mut |command: Command| async move {
         match command {
-            Command::Help => println!("{}", Command::help()),
-            Command::Quit => break,
             Command::Quote => {
                 println!("Veni, Vidi, Vici");
             }
+            Command::Remind { seconds, message } => {
+                tokio::spawn(async move {
+                    tokio::time::sleep(Duration::from_secs(seconds)).await;
+                    tracing::info!("{}", message);
+                });
+            }
             cmd => println!("{:?}", cmd),
         }
-    }
 
-    Ok(())
+        Ok(ControlFlow::Continue)
+    })
+    .await
 }
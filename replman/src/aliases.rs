@@ -0,0 +1,116 @@
+use std::collections::{BTreeMap, HashSet};
+
+/// Aliases defined at runtime, e.g. with `alias st = status --verbose`
+///
+/// An alias replaces the command word of a line, the rest of the line is
+/// appended to the expansion.
+#[derive(Debug, Clone, Default)]
+pub struct Aliases {
+    aliases: BTreeMap<String, String>,
+}
+
+impl Aliases {
+    pub fn set(
+        &mut self,
+        name: impl Into<String>,
+        expansion: impl Into<String>,
+    ) {
+        self.aliases.insert(name.into(), expansion.into());
+    }
+
+    pub fn remove(&mut self, name: &str) -> Option<String> {
+        self.aliases.remove(name)
+    }
+
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.aliases.get(name).map(String::as_str)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.aliases.iter().map(|(k, v)| (k.as_str(), v.as_str()))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.aliases.is_empty()
+    }
+
+    /// Expands the command word of `line`
+    ///
+    /// Like in a shell, an alias is not expanded again within its own
+    /// expansion, so `alias ls = ls --all` is fine and mutually recursive
+    /// aliases can't loop forever.
+    pub fn expand(&self, line: &str) -> String {
+        let mut line = line.to_string();
+        let mut expanded = HashSet::new();
+
+        loop {
+            let (cmd_word, rest) = split_cmd_word(&line);
+
+            let expansion = match self.aliases.get(cmd_word) {
+                Some(expansion) if !expanded.contains(cmd_word) => expansion,
+                _ => return line,
+            };
+
+            expanded.insert(cmd_word.to_string());
+            line = format!("{}{}", expansion.trim(), rest);
+        }
+    }
+}
+
+/// Splits a line into its command word and the rest (including the leading
+/// whitespace)
+pub(crate) fn split_cmd_word(line: &str) -> (&str, &str) {
+    let line = line.trim_start();
+    let end = line.find(char::is_whitespace).unwrap_or(line.len());
+
+    line.split_at(end)
+}
+
+/// Parses the arguments of the `alias` builtin, `name = expansion` or
+/// `name=expansion`
+pub(crate) fn parse_definition(args: &str) -> Option<(&str, &str)> {
+    let (name, expansion) = args.split_once('=')?;
+    let name = name.trim();
+    let expansion = expansion.trim();
+
+    if name.is_empty() || name.contains(char::is_whitespace) {
+        return None;
+    }
+
+    Some((name, expansion))
+}
+
+#[cfg(test)]
+mod tests {
+    use test_case::test_case;
+
+    use super::*;
+
+    fn aliases() -> Aliases {
+        let mut aliases = Aliases::default();
+        aliases.set("st", "status --verbose");
+        aliases.set("ls", "ls --all");
+        aliases.set("a", "b 1");
+        aliases.set("b", "a 2");
+        aliases.set("s", "st");
+        aliases
+    }
+
+    #[test_case("st", "status --verbose" ; "Simple")]
+    #[test_case("st now", "status --verbose now" ; "With arguments")]
+    #[test_case("s", "status --verbose" ; "Nested")]
+    #[test_case("ls", "ls --all" ; "Self referencing")]
+    #[test_case("a", "a 2 1" ; "Mutually recursive")]
+    #[test_case("status", "status" ; "Not an alias")]
+    fn expand(line: &str, exp: &str) {
+        assert_eq!(aliases().expand(line), exp);
+    }
+
+    #[test_case("st = status --verbose", Some(("st", "status --verbose")) ; "Spaced")]
+    #[test_case("st=status", Some(("st", "status")) ; "Compact")]
+    #[test_case("st status", None ; "Missing equals sign")]
+    #[test_case("= status", None ; "Missing name")]
+    fn definition(args: &str, exp: Option<(&str, &str)>) {
+        assert_eq!(parse_definition(args), exp);
+    }
+}
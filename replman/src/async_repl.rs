@@ -0,0 +1,323 @@
+use std::future::Future;
+use std::io;
+use std::sync::{mpsc, Arc, Mutex};
+
+use tokio::sync::oneshot;
+
+use crate::script::Script;
+use crate::{
+    ControlFlow, LineSource, Output, Prompt, ReadLine, ReadOutcome, Redirects,
+    Repl, ReplCmd,
+};
+
+/// Async version of [`crate::ReplHandler`], used by [`Repl::run_async`]
+pub trait AsyncReplHandler<C>
+where
+    C: ReplCmd,
+{
+    fn handle(
+        &mut self,
+        cmd: C,
+    ) -> impl Future<Output = anyhow::Result<ControlFlow>>;
+}
+
+impl<C, F, Fut> AsyncReplHandler<C> for F
+where
+    C: ReplCmd,
+    F: FnMut(C) -> Fut,
+    Fut: Future<Output = anyhow::Result<ControlFlow>>,
+{
+    fn handle(
+        &mut self,
+        cmd: C,
+    ) -> impl Future<Output = anyhow::Result<ControlFlow>> {
+        self(cmd)
+    }
+}
+
+enum ReadRequest {
+    Line {
+        prompt: Prompt,
+        /// Initial contents of the line
+        initial: Option<String>,
+    },
+    /// Reads the next line of the script, which is sent back with it
+    Script(Script),
+}
+
+enum ReadResponse {
+    Line(io::Result<ReadLine>),
+    Script(Script, io::Result<Option<String>>),
+}
+
+/// A dedicated thread that reads from the line source and the scripts, so
+/// that the async runtime is never blocked on input
+pub(crate) struct Reader {
+    requests: mpsc::Sender<(ReadRequest, oneshot::Sender<ReadResponse>)>,
+    /// Response to the last request, kept until it's received so that a
+    /// read whose future was dropped is picked up by the next one
+    pending: Option<oneshot::Receiver<ReadResponse>>,
+}
+
+impl Reader {
+    fn spawn(source: Arc<Mutex<Box<dyn LineSource>>>) -> Self {
+        let (requests, rx) =
+            mpsc::channel::<(ReadRequest, oneshot::Sender<ReadResponse>)>();
+
+        std::thread::Builder::new()
+            .name("replman-reader".to_string())
+            .spawn(move || {
+                // Exits once the repl, and with it the sender, is dropped
+                for (request, respond) in rx {
+                    let response = match request {
+                        ReadRequest::Line { prompt, initial } => {
+                            ReadResponse::Line(
+                                source
+                                    .lock()
+                                    .expect("Line source lock poisoned")
+                                    .read_line(&prompt, initial.as_deref()),
+                            )
+                        }
+                        ReadRequest::Script(mut script) => {
+                            let read = script.read_line();
+                            ReadResponse::Script(script, read)
+                        }
+                    };
+
+                    let _ = respond.send(response);
+                }
+            })
+            .expect("Failed to spawn the reader thread");
+
+        Self {
+            requests,
+            pending: None,
+        }
+    }
+
+    fn send(&mut self, request: ReadRequest) -> anyhow::Result<()> {
+        let (respond, response) = oneshot::channel();
+        self.requests
+            .send((request, respond))
+            .map_err(|_| anyhow::anyhow!("The reader thread has exited"))?;
+        self.pending = Some(response);
+
+        Ok(())
+    }
+
+    /// Waits for the response to the last request
+    async fn response(&mut self) -> anyhow::Result<ReadResponse> {
+        let pending = self
+            .pending
+            .as_mut()
+            .ok_or_else(|| anyhow::anyhow!("No read is pending"))?;
+        let response = pending.await;
+        self.pending = None;
+
+        Ok(response?)
+    }
+}
+
+impl Repl {
+    /// Like [`Repl::read_command`], but doesn't block the async runtime
+    /// while waiting for input
+    ///
+    /// Lines are read on a separate thread, from the line source as well as
+    /// from scripts. The read is cancel safe: if the future is dropped, e.g.
+    /// in `tokio::select!`, the line being read is returned by the next
+    /// async read. Sync reads shouldn't be mixed in while one is pending, as
+    /// they wait for the pending line and then read another one.
+    pub async fn read_command_async<R>(
+        &mut self,
+    ) -> anyhow::Result<ReadOutcome<R>>
+    where
+        R: ReplCmd,
+    {
+        Ok(self
+            .read_async(self.redirects(false))
+            .await?
+            .map(|(cmd, _)| cmd))
+    }
+
+    /// Like [`Repl::read_command_with_output`], but doesn't block the async
+    /// runtime while waiting for input
+    pub async fn read_command_with_output_async<R>(
+        &mut self,
+    ) -> anyhow::Result<ReadOutcome<(R, Output)>>
+    where
+        R: ReplCmd,
+    {
+        self.read_async(self.redirects(true)).await
+    }
+
+    /// Like [`Repl::run`], with an async handler
+    pub async fn run_async<R, H>(
+        &mut self,
+        handler: &mut H,
+    ) -> anyhow::Result<()>
+    where
+        R: ReplCmd,
+        H: AsyncReplHandler<R>,
+    {
+        loop {
+            let cmd = match self.read_command_async::<R>().await? {
+                ReadOutcome::Command(cmd) => cmd,
+                ReadOutcome::Interrupted => continue,
+                ReadOutcome::Eof => return Ok(()),
+            };
+
+            match handler.handle(cmd).await {
+                Ok(ControlFlow::Continue) => (),
+                Ok(ControlFlow::Exit) => return Ok(()),
+                Err(err) => self.report_error(err)?,
+            }
+        }
+    }
+
+    async fn read_async<R>(
+        &mut self,
+        redirects: Redirects,
+    ) -> anyhow::Result<ReadOutcome<(R, Output)>>
+    where
+        R: ReplCmd,
+    {
+        loop {
+            let line = match self.next_line_async::<R>(redirects).await? {
+                Some(line) => line,
+                None => continue,
+            };
+
+            if let Some(ret) = self.handle_read(line, redirects) {
+                return ret;
+            }
+        }
+    }
+
+    /// Reads the next line from the innermost script or the line source
+    ///
+    /// Returns `None` if a script ended and the next line should be read.
+    async fn next_line_async<R>(
+        &mut self,
+        redirects: Redirects,
+    ) -> anyhow::Result<Option<io::Result<ReadLine>>>
+    where
+        R: ReplCmd,
+    {
+        if self.reader().pending.is_none() {
+            let request = match self.scripts.pop() {
+                Some(script) => ReadRequest::Script(script),
+                None if !self.interactive => {
+                    return Ok(Some(Ok(ReadLine::Eof)));
+                }
+                None => {
+                    self.update_context::<R>(redirects);
+                    ReadRequest::Line {
+                        prompt: self.next_prompt(),
+                        initial: self.failed_line.take(),
+                    }
+                }
+            };
+
+            self.reader().send(request)?;
+        }
+
+        Ok(match self.reader().response().await? {
+            ReadResponse::Line(line) => Some(line),
+            ReadResponse::Script(script, read) => {
+                self.scripts.push(script);
+                self.handle_script_read(read)
+            }
+        })
+    }
+
+    fn reader(&mut self) -> &mut Reader {
+        let source = self.source.clone();
+        self.reader.get_or_insert_with(|| Reader::spawn(source))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+    use std::time::Duration;
+
+    use super::*;
+
+    #[derive(Debug, PartialEq)]
+    struct Echo(String);
+
+    impl ReplCmd for Echo {
+        fn help() -> &'static str {
+            "echo <0>\n"
+        }
+
+        fn parse<I>(_line: &str, mut parts: I) -> anyhow::Result<Self>
+        where
+            I: Iterator<Item = anyhow::Result<String>>,
+        {
+            match parts.next().transpose()?.as_deref() {
+                Some("echo") => Ok(Self(
+                    parts
+                        .next()
+                        .transpose()?
+                        .ok_or_else(|| anyhow::anyhow!("Missing field"))?,
+                )),
+                _ => anyhow::bail!("unrecognized command"),
+            }
+        }
+    }
+
+    /// Blocks until a line is sent
+    struct ChannelSource(mpsc::Receiver<String>);
+
+    impl LineSource for ChannelSource {
+        fn read_line(
+            &mut self,
+            _prompt: &Prompt,
+            _initial: Option<&str>,
+        ) -> io::Result<ReadLine> {
+            Ok(self.0.recv().map_or(ReadLine::Eof, ReadLine::Line))
+        }
+
+        fn add_history_entry(&mut self, _line: &str) {}
+
+        fn history(&self) -> Vec<String> {
+            vec![]
+        }
+    }
+
+    #[tokio::test]
+    async fn reads_scripts() {
+        let script = Cursor::new("echo a\n# comment\necho b\n");
+        let mut repl = Repl::from_reader(script, "script");
+
+        let mut cmds = vec![];
+        while let ReadOutcome::Command(cmd) =
+            repl.read_command_async::<Echo>().await.unwrap()
+        {
+            cmds.push(cmd);
+        }
+
+        assert_eq!(cmds, vec![Echo("a".to_string()), Echo("b".to_string())]);
+    }
+
+    #[tokio::test]
+    async fn cancel_safe() {
+        let (lines, rx) = mpsc::channel();
+        let mut repl = Repl::builder()
+            .line_source(ChannelSource(rx))
+            .build()
+            .unwrap();
+
+        tokio::select! {
+            _ = repl.read_command_async::<Echo>() => panic!("No line was sent"),
+            _ = tokio::time::sleep(Duration::from_millis(10)) => (),
+        }
+
+        lines.send("echo pending".to_string()).unwrap();
+        assert_eq!(
+            repl.read_command_async::<Echo>().await.unwrap(),
+            ReadOutcome::Command(Echo("pending".to_string()))
+        );
+    }
+}
@@ -0,0 +1,487 @@
+use std::io::{self, BufRead, IsTerminal};
+use std::path::PathBuf;
+
+use crate::script::Script;
+use crate::{
+    BufReadSource, ErrorPrinter, LineSource, Prompt, PromptSource, Repl, Theme,
+    TokenizerConfig,
+};
+
+/// Key bindings of the line editor
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EditMode {
+    Emacs,
+    Vi,
+}
+
+/// What happens when the line editor rings the bell, e.g. on a failed
+/// completion
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BellStyle {
+    Audible,
+    Visible,
+    None,
+}
+
+/// What happens when the user presses Ctrl-C at the prompt
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InterruptBehavior {
+    /// Discards the line and shows a fresh prompt
+    ClearLine,
+    /// Like [`InterruptBehavior::ClearLine`], but a second Ctrl-C in a row
+    /// ends the input
+    ConfirmExit,
+    /// Ends the input, like Ctrl-D
+    Exit,
+    /// Returns [`crate::ReadOutcome::Interrupted`] to the caller
+    Return,
+}
+
+/// Which lines are recorded in the history
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HistoryPolicy {
+    /// Lines that were parsed without errors, including builtins
+    ///
+    /// A command is recorded before it's handled, so it's kept even if
+    /// handling it fails.
+    Successful,
+    /// Also lines that failed, so that they can be fixed
+    All,
+    None,
+}
+
+/// Configures and creates a [`Repl`]
+///
+/// The key bindings, bell and history size only apply to the default
+/// rustyline line source.
+///
+/// ```no_run
+/// # use replman::prelude::*;
+/// let repl = Repl::builder()
+///     .prompt("db> ")
+///     .edit_mode(EditMode::Vi)
+///     .history_file("history.txt")
+///     .max_history_size(1000)
+///     .build()?;
+/// # Ok::<(), anyhow::Error>(())
+/// ```
+pub struct ReplBuilder {
+    prompt: PromptSource,
+    edit_mode: EditMode,
+    bell_style: BellStyle,
+    interrupt_behavior: InterruptBehavior,
+    history_file: Option<PathBuf>,
+    max_history_size: usize,
+    history_ignore_dups: bool,
+    history_ignore_space: bool,
+    history_policy: HistoryPolicy,
+    retry_failed_line: bool,
+    skip_blank_lines: bool,
+    pipes: bool,
+    tokenizer: TokenizerConfig,
+    theme: Option<Theme>,
+    hints: bool,
+    validate_input: bool,
+    error_printer: ErrorPrinter,
+    script: Option<ScriptSource>,
+    rc_file: Option<PathBuf>,
+    stop_on_error: bool,
+    echo: bool,
+    line_source: Option<Box<dyn LineSource>>,
+    recording: Option<PathBuf>,
+}
+
+enum ScriptSource {
+    Reader(Script),
+    File(PathBuf),
+}
+
+impl Default for ReplBuilder {
+    fn default() -> Self {
+        Self {
+            prompt: PromptSource::Static(Prompt::new("> ")),
+            edit_mode: EditMode::Emacs,
+            bell_style: BellStyle::Audible,
+            interrupt_behavior: InterruptBehavior::ClearLine,
+            history_file: None,
+            max_history_size: 100,
+            history_ignore_dups: true,
+            history_ignore_space: false,
+            history_policy: HistoryPolicy::Successful,
+            retry_failed_line: false,
+            skip_blank_lines: true,
+            pipes: false,
+            tokenizer: TokenizerConfig::default(),
+            theme: Some(Theme::default()),
+            hints: true,
+            validate_input: false,
+            error_printer: Box::new(default_error_printer),
+            script: None,
+            rc_file: None,
+            stop_on_error: true,
+            echo: false,
+            line_source: None,
+            recording: None,
+        }
+    }
+}
+
+pub(crate) fn default_error_printer(err: &anyhow::Error) {
+    eprintln!("{:#}", err);
+}
+
+impl ReplBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn prompt(mut self, prompt: impl Into<Prompt>) -> Self {
+        self.prompt = PromptSource::Static(prompt.into());
+        self
+    }
+
+    /// See [`Repl::set_prompt_provider`]
+    pub fn prompt_provider<P>(
+        mut self,
+        mut provider: impl FnMut() -> P + Send + 'static,
+    ) -> Self
+    where
+        P: Into<Prompt>,
+    {
+        self.prompt =
+            PromptSource::Provider(Box::new(move || provider().into()));
+        self
+    }
+
+    pub fn edit_mode(mut self, edit_mode: EditMode) -> Self {
+        self.edit_mode = edit_mode;
+        self
+    }
+
+    pub fn bell_style(mut self, bell_style: BellStyle) -> Self {
+        self.bell_style = bell_style;
+        self
+    }
+
+    /// See [`Repl::set_interrupt_behavior`]
+    pub fn interrupt_behavior(mut self, behavior: InterruptBehavior) -> Self {
+        self.interrupt_behavior = behavior;
+        self
+    }
+
+    /// Loads history from `path` at startup and appends every command to it
+    ///
+    /// A missing file is created on the first command.
+    pub fn history_file(mut self, path: impl Into<PathBuf>) -> Self {
+        self.history_file = Some(path.into());
+        self
+    }
+
+    /// Maximum number of history entries kept, in memory and in the file
+    pub fn max_history_size(mut self, size: usize) -> Self {
+        self.max_history_size = size;
+        self
+    }
+
+    /// Don't record a command that's the same as the previous one
+    pub fn history_ignore_dups(mut self, ignore_dups: bool) -> Self {
+        self.history_ignore_dups = ignore_dups;
+        self
+    }
+
+    /// Don't record lines starting with a space
+    pub fn history_ignore_space(mut self, ignore_space: bool) -> Self {
+        self.history_ignore_space = ignore_space;
+        self
+    }
+
+    /// See [`Repl::set_history_policy`]
+    pub fn history_policy(mut self, policy: HistoryPolicy) -> Self {
+        self.history_policy = policy;
+        self
+    }
+
+    /// Show a line that failed again in the next prompt, to be fixed
+    pub fn retry_failed_line(mut self, enabled: bool) -> Self {
+        self.retry_failed_line = enabled;
+        self
+    }
+
+    /// Skip empty lines instead of passing them to [`crate::ReplCmd::parse`],
+    /// enabled by default
+    pub fn skip_blank_lines(mut self, skip_blank_lines: bool) -> Self {
+        self.skip_blank_lines = skip_blank_lines;
+        self
+    }
+
+    /// See [`Repl::set_pipes_enabled`]
+    pub fn pipes(mut self, enabled: bool) -> Self {
+        self.pipes = enabled;
+        self
+    }
+
+    pub fn tokenizer(mut self, config: TokenizerConfig) -> Self {
+        self.tokenizer = config;
+        self
+    }
+
+    /// Colors the input line as it's typed, enabled by default
+    pub fn highlighting(mut self, enabled: bool) -> Self {
+        self.theme = enabled.then(|| self.theme.unwrap_or_default());
+        self
+    }
+
+    /// Sets the colors of the input line and enables highlighting
+    pub fn theme(mut self, theme: Theme) -> Self {
+        self.theme = Some(theme);
+        self
+    }
+
+    /// Show the remaining arguments of the command being typed after the
+    /// cursor, enabled by default
+    pub fn hints(mut self, enabled: bool) -> Self {
+        self.hints = enabled;
+        self
+    }
+
+    /// Check the line when Enter is pressed and keep it in the editor, with
+    /// the error below it, if it can't be parsed
+    pub fn validate_input(mut self, enabled: bool) -> Self {
+        self.validate_input = enabled;
+        self
+    }
+
+    /// Called with the errors of lines that couldn't be executed, prints
+    /// them to stderr by default
+    pub fn error_printer(
+        mut self,
+        error_printer: impl Fn(&anyhow::Error) + Send + 'static,
+    ) -> Self {
+        self.error_printer = Box::new(error_printer);
+        self
+    }
+
+    /// See [`Repl::from_reader`]
+    pub fn script(
+        mut self,
+        reader: impl BufRead + Send + 'static,
+        name: impl Into<String>,
+    ) -> Self {
+        self.script =
+            Some(ScriptSource::Reader(Script::new(reader, name.into())));
+        self
+    }
+
+    /// Runs the commands in the file at `path`, see [`Repl::from_reader`]
+    pub fn script_file(mut self, path: impl Into<PathBuf>) -> Self {
+        self.script = Some(ScriptSource::File(path.into()));
+        self
+    }
+
+    /// Runs the commands in the file at `path` before the first prompt, e.g.
+    /// to define aliases and variables
+    ///
+    /// Nothing happens if the file doesn't exist. The first line that fails
+    /// ends the startup with an error of [`Repl::read_command`] or
+    /// [`Repl::run`].
+    ///
+    /// ```no_run
+    /// # use std::path::PathBuf;
+    /// # use replman::prelude::*;
+    /// let home = PathBuf::from(std::env::var_os("HOME").unwrap_or_default());
+    /// let repl = Repl::builder().rc_file(home.join(".myapprc")).build()?;
+    /// # Ok::<(), anyhow::Error>(())
+    /// ```
+    pub fn rc_file(mut self, path: impl Into<PathBuf>) -> Self {
+        self.rc_file = Some(path.into());
+        self
+    }
+
+    /// Stop a script at the first line that fails, with an error of
+    /// [`Repl::read_command`] or [`Repl::run`], enabled by default
+    ///
+    /// Otherwise errors are printed and the script continues with the next
+    /// line.
+    pub fn stop_on_error(mut self, stop_on_error: bool) -> Self {
+        self.stop_on_error = stop_on_error;
+        self
+    }
+
+    /// Print the lines of a script after the prompt, as if they were typed
+    pub fn echo(mut self, echo: bool) -> Self {
+        self.echo = echo;
+        self
+    }
+
+    /// Reads lines from `source` instead of the default line editor
+    ///
+    /// Unlike with the default, stdin is never read as a script when it
+    /// isn't a terminal.
+    pub fn line_source(mut self, source: impl LineSource + 'static) -> Self {
+        self.line_source = Some(Box::new(source));
+        self
+    }
+
+    /// Records the session to the file at `path`, overwriting it, to be
+    /// replayed with [`Repl::replay`]
+    ///
+    /// Every record is a line made of the time it was written, as seconds
+    /// since the Unix epoch with milliseconds, the kind of the record and its
+    /// text, separated by single spaces:
+    ///
+    /// ```text
+    /// 1760870400.120 in greet "John Doe"
+    /// 1760870400.121 cmd Greet { name: "John Doe" }
+    /// 1760870400.121 out Hello, John Doe!
+    /// 1760870402.310 in gret
+    /// 1760870402.311 err Failed to parse command: unrecognized command 'gret'
+    /// ```
+    ///
+    /// The kinds are:
+    ///
+    /// - `in`: a line read from the user or a script
+    /// - `cmd`: the command it was parsed into, formatted with [`Debug`]. See
+    ///   [`crate::ReplCmd::debug_record`], commands without `Debug` aren't
+    ///   recorded.
+    /// - `out`: a line printed with the [`crate::ReplPrinter`]. While
+    ///   recording, the [`crate::Output`] of commands that would go to stdout
+    ///   is printed with it too.
+    /// - `err`: an error that was printed
+    ///
+    /// Backslashes, newlines and carriage returns in the text are escaped as
+    /// `\\`, `\n` and `\r`.
+    pub fn record(mut self, path: impl Into<PathBuf>) -> Self {
+        self.recording = Some(path.into());
+        self
+    }
+
+    pub fn build(mut self) -> anyhow::Result<Repl> {
+        let stdin_script = self.line_source.is_none()
+            && self.script.is_none()
+            && !io::stdin().is_terminal();
+
+        let mut source = match self.line_source.take() {
+            Some(source) => source,
+            // The script is read instead, so there's no need for an editor
+            None if self.script.is_some() || stdin_script => {
+                Box::new(BufReadSource::new(io::empty()))
+            }
+            None => self.default_source()?,
+        };
+
+        if let Some(history_file) = &self.history_file {
+            match source.load_history(history_file) {
+                Ok(()) => (),
+                Err(err) if err.kind() == io::ErrorKind::NotFound => (),
+                Err(err) => return Err(err.into()),
+            }
+        }
+
+        let mut repl = Repl::with_source(source);
+        repl.prompt = self.prompt;
+        repl.interrupt_behavior = self.interrupt_behavior;
+        repl.history_file = self.history_file;
+        repl.history_ignore_space = self.history_ignore_space;
+        repl.history_policy = self.history_policy;
+        repl.retry_failed_line = self.retry_failed_line;
+        repl.skip_blank_lines = self.skip_blank_lines;
+        repl.pipes = self.pipes;
+        repl.set_tokenizer_config(self.tokenizer);
+        repl.set_theme(self.theme);
+        repl.hints = self.hints;
+        repl.error_printer = self.error_printer;
+        repl.stop_on_error = self.stop_on_error;
+        repl.echo = self.echo;
+        repl.validate_input = self.validate_input;
+
+        if let Some(path) = &self.recording {
+            repl.start_recording(path)?;
+        }
+
+        match self.script {
+            Some(ScriptSource::Reader(script)) => repl.set_script(script),
+            Some(ScriptSource::File(path)) => {
+                repl.set_script(Script::open(&path)?)
+            }
+            None if stdin_script => repl.set_script(Script::stdin()),
+            None => (),
+        }
+
+        if let Some(path) = self.rc_file.filter(|path| path.exists()) {
+            let mut script = Script::open(&path)?;
+            script.startup = true;
+            repl.scripts.push(script);
+        }
+
+        Ok(repl)
+    }
+
+    #[cfg(feature = "rustyline")]
+    fn default_source(&self) -> anyhow::Result<Box<dyn LineSource>> {
+        let config = self.rustyline_config();
+        Ok(Box::new(crate::RustylineSource::with_config(config)?))
+    }
+
+    #[cfg(feature = "rustyline")]
+    fn rustyline_config(&self) -> rustyline::Config {
+        use rustyline::config::{BellStyle as RlBellStyle, Config};
+
+        let edit_mode = match self.edit_mode {
+            EditMode::Emacs => rustyline::EditMode::Emacs,
+            EditMode::Vi => rustyline::EditMode::Vi,
+        };
+
+        let bell_style = match self.bell_style {
+            BellStyle::Audible => RlBellStyle::Audible,
+            BellStyle::Visible => RlBellStyle::Visible,
+            BellStyle::None => RlBellStyle::None,
+        };
+
+        Config::builder()
+            .edit_mode(edit_mode)
+            .bell_style(bell_style)
+            .max_history_size(self.max_history_size)
+            .history_ignore_dups(self.history_ignore_dups)
+            .build()
+    }
+
+    #[cfg(not(feature = "rustyline"))]
+    fn default_source(&self) -> anyhow::Result<Box<dyn LineSource>> {
+        Ok(Box::new(crate::BufReadSource::stdin()))
+    }
+}
+
+#[cfg(all(test, feature = "rustyline"))]
+mod tests {
+    use rustyline::config::{BellStyle as RlBellStyle, HistoryDuplicates};
+
+    use super::*;
+
+    #[test]
+    fn default_rustyline_config() {
+        let config = ReplBuilder::new().rustyline_config();
+
+        assert_eq!(config.edit_mode(), rustyline::EditMode::Emacs);
+        assert_eq!(config.bell_style(), RlBellStyle::Audible);
+        assert_eq!(config.max_history_size(), 100);
+        assert_eq!(
+            config.history_duplicates(),
+            HistoryDuplicates::IgnoreConsecutive
+        );
+    }
+
+    #[test]
+    fn rustyline_config() {
+        let config = ReplBuilder::new()
+            .edit_mode(EditMode::Vi)
+            .bell_style(BellStyle::None)
+            .max_history_size(5)
+            .history_ignore_dups(false)
+            .rustyline_config();
+
+        assert_eq!(config.edit_mode(), rustyline::EditMode::Vi);
+        assert_eq!(config.bell_style(), RlBellStyle::None);
+        assert_eq!(config.max_history_size(), 5);
+        assert_eq!(config.history_duplicates(), HistoryDuplicates::AlwaysAdd);
+    }
+}
@@ -0,0 +1,63 @@
+use std::fmt;
+
+/// Description of a command, generated by the derive
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CommandInfo {
+    pub name: &'static str,
+    pub aliases: &'static [&'static str],
+    /// Prefixes of `#[replman(starts_with = "...")]`
+    pub starts_with: &'static [&'static str],
+    pub args: &'static [ArgInfo],
+}
+
+impl CommandInfo {
+    /// Whether `name` is the name or one of the aliases of the command
+    pub fn is_named(&self, name: &str) -> bool {
+        self.name == name || self.aliases.contains(&name)
+    }
+
+    /// Whether the command takes the remainder of the line
+    pub fn is_raw(&self) -> bool {
+        self.args.iter().any(|arg| arg.raw)
+    }
+}
+
+/// The command `word` is parsed as, names and aliases are matched before
+/// the `starts_with` prefixes like in the derived [`crate::ReplCmd::parse`]
+pub(crate) fn find_command<'a>(
+    infos: &'a [CommandInfo],
+    word: &str,
+) -> Option<&'a CommandInfo> {
+    infos.iter().find(|info| info.is_named(word)).or_else(|| {
+        infos.iter().find(|info| {
+            info.starts_with
+                .iter()
+                .any(|prefix| word.starts_with(prefix))
+        })
+    })
+}
+
+/// Description of an argument of a command
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ArgInfo {
+    /// Field name, or its index in a tuple variant
+    pub name: &'static str,
+    /// Type as written in the source, without the `Option`
+    pub ty: &'static str,
+    /// Has a default value or is an `Option`
+    pub optional: bool,
+    /// Takes the remainder of the line, see `#[replman(raw)]`
+    pub raw: bool,
+}
+
+/// Formats the argument like in a usage line, `<name: type>`, `[name: type]`
+/// if it's optional or `<name...>` if it's raw
+impl fmt::Display for ArgInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (self.raw, self.optional) {
+            (true, _) => write!(f, "<{}...>", self.name),
+            (false, true) => write!(f, "[{}: {}]", self.name, self.ty),
+            (false, false) => write!(f, "<{}: {}>", self.name, self.ty),
+        }
+    }
+}
@@ -0,0 +1,156 @@
+//! Used by the derived [`crate::ToCommandLine`] and
+//! [`crate::ReplCmd::debug_record`], not part of the API
+//!
+//! The derive calls methods on references to these wrappers. The impls on
+//! the wrappers themselves need [`ToArg`], `PartialEq` or `Debug` and are
+//! preferred, the impls on references are picked if the type doesn't
+//! implement them. That way enums with arguments of other types, or without
+//! `Debug`, still derive [`crate::ReplCmd`].
+
+use std::any::type_name;
+use std::fmt::Debug;
+
+use crate::{quote_arg, ToArg, WrittenLine};
+
+/// Writes the command `name` followed by its arguments
+///
+/// Every argument comes with its name, for errors, its text, `None` if it's
+/// left out, and whether it's equal to its default.
+pub fn write_line(
+    name: &str,
+    mut args: Vec<(&str, anyhow::Result<Option<String>>, bool)>,
+) -> WrittenLine {
+    // Missing trailing arguments are parsed as their defaults
+    while let Some((_, Ok(None), _) | (_, _, true)) = args.last() {
+        args.pop();
+    }
+
+    let mut line = name.to_string();
+    let mut error = None;
+    for (arg_name, arg, _) in args {
+        let arg = match arg {
+            Ok(Some(arg)) => match quote_arg(&arg) {
+                Ok(quoted) => quoted.into_owned(),
+                Err(err) => {
+                    error.get_or_insert(err);
+                    format!("'{}'", arg)
+                }
+            },
+            // A missing argument would shift the ones after it
+            Ok(None) => {
+                error.get_or_insert_with(|| {
+                    anyhow::anyhow!(
+                        "Argument '{}' of '{}' is None, but arguments after \
+                         it aren't",
+                        arg_name,
+                        name
+                    )
+                });
+                "''".to_string()
+            }
+            Err(err) => {
+                error.get_or_insert(err);
+                "?".to_string()
+            }
+        };
+
+        line.push(' ');
+        line.push_str(&arg);
+    }
+
+    WrittenLine { line, error }
+}
+
+/// Writes a raw command, its remainder is written as is
+pub fn write_raw(name: &str, remainder: &str) -> WrittenLine {
+    let line = if remainder.is_empty() {
+        name.to_string()
+    } else {
+        format!("{} {}", name, remainder)
+    };
+
+    WrittenLine { line, error: None }
+}
+
+/// An argument to write
+pub struct Arg<'a, T>(pub &'a T);
+
+pub trait WriteArg {
+    fn write_arg(&self) -> anyhow::Result<Option<String>>;
+}
+
+impl<T> WriteArg for Arg<'_, T>
+where
+    T: ToArg,
+{
+    fn write_arg(&self) -> anyhow::Result<Option<String>> {
+        Ok(self.0.to_arg())
+    }
+}
+
+pub trait WriteArgFallback {
+    fn write_arg(&self) -> anyhow::Result<Option<String>>;
+}
+
+impl<T> WriteArgFallback for &Arg<'_, T> {
+    fn write_arg(&self) -> anyhow::Result<Option<String>> {
+        anyhow::bail!(
+            "{} doesn't implement ToArg and can't be written as an argument",
+            type_name::<T>()
+        )
+    }
+}
+
+/// An argument and its default value
+pub struct Defaulted<'a, T>(pub &'a T, pub T);
+
+pub trait IsDefault {
+    fn is_default(&self) -> bool;
+}
+
+impl<T> IsDefault for Defaulted<'_, T>
+where
+    T: PartialEq,
+{
+    fn is_default(&self) -> bool {
+        *self.0 == self.1
+    }
+}
+
+/// Arguments that can't be compared are always written
+pub trait IsDefaultFallback {
+    fn is_default(&self) -> bool;
+}
+
+impl<T> IsDefaultFallback for &Defaulted<'_, T> {
+    fn is_default(&self) -> bool {
+        false
+    }
+}
+
+/// A command to record
+pub struct Record<'a, T>(pub &'a T);
+
+pub trait FormatRecord {
+    fn format_record(&self) -> Option<String>;
+}
+
+impl<T> FormatRecord for Record<'_, T>
+where
+    T: Debug,
+{
+    fn format_record(&self) -> Option<String> {
+        Some(format!("{:?}", self.0))
+    }
+}
+
+/// Commands without `Debug` aren't recorded
+pub trait FormatRecordFallback {
+    fn format_record(&self) -> Option<String>;
+}
+
+impl<T> FormatRecordFallback for &Record<'_, T> {
+    fn format_record(&self) -> Option<String> {
+        None
+    }
+}
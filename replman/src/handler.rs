@@ -0,0 +1,48 @@
+use crate::ReplCmd;
+
+/// Whether [`crate::Repl::run`] should keep reading commands
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ControlFlow {
+    Continue,
+    Exit,
+}
+
+/// Executes the commands read by [`crate::Repl::run`]
+///
+/// An error returned from [`ReplHandler::handle`] is printed and the session
+/// continues.
+///
+/// Closures taking a command are handlers too:
+///
+/// ```no_run
+/// # use replman::prelude::*;
+/// #[derive(ReplCmd)]
+/// enum Command {
+///     Greet { name: String },
+/// }
+///
+/// Repl::new().run(&mut |cmd: Command| {
+///     match cmd {
+///         Command::Greet { name } => println!("Hello, {}!", name),
+///     }
+///
+///     Ok(ControlFlow::Continue)
+/// })?;
+/// # Ok::<(), anyhow::Error>(())
+/// ```
+pub trait ReplHandler<C>
+where
+    C: ReplCmd,
+{
+    fn handle(&mut self, cmd: C) -> anyhow::Result<ControlFlow>;
+}
+
+impl<C, F> ReplHandler<C> for F
+where
+    C: ReplCmd,
+    F: FnMut(C) -> anyhow::Result<ControlFlow>,
+{
+    fn handle(&mut self, cmd: C) -> anyhow::Result<ControlFlow> {
+        self(cmd)
+    }
+}
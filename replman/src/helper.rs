@@ -0,0 +1,142 @@
+use std::borrow::Cow;
+
+use rustyline::completion::Completer;
+use rustyline::highlight::Highlighter;
+use rustyline::hint::Hinter;
+use rustyline::validate::{ValidationContext, ValidationResult, Validator};
+use rustyline::{Context, Helper};
+
+use crate::command_info::CommandInfo;
+use crate::highlight::{highlight, Theme};
+use crate::hint::{hint, ReplHint};
+use crate::line_source::LineValidator;
+use crate::prompt::RenderedPrompt;
+use crate::tokenizer::TokenizerConfig;
+
+/// The rustyline helper installed by [`crate::RustylineSource`]
+#[derive(Default)]
+pub(crate) struct ReplHelper {
+    /// Command words available for completion, refreshed before every line
+    pub commands: Vec<String>,
+    /// Arguments of the commands, for hints
+    pub infos: &'static [CommandInfo],
+    pub hints: bool,
+    /// The prompt of the line currently being edited
+    pub prompt: RenderedPrompt,
+    /// Colors of the input line, `None` if highlighting is disabled
+    pub theme: Option<Theme>,
+    pub tokenizer: TokenizerConfig,
+    /// Checks the line when Enter is pressed, `None` if any line is accepted
+    pub validator: Option<LineValidator>,
+}
+
+impl Completer for ReplHelper {
+    type Candidate = String;
+
+    fn complete(
+        &self,
+        line: &str,
+        pos: usize,
+        _ctx: &Context<'_>,
+    ) -> rustyline::Result<(usize, Vec<Self::Candidate>)> {
+        let prefix = &line[..pos];
+        let start = prefix.len() - prefix.trim_start().len();
+        let prefix = &prefix[start..];
+
+        // Only the command word is completed
+        if prefix.contains(char::is_whitespace) {
+            return Ok((pos, vec![]));
+        }
+
+        let mut candidates: Vec<_> = self
+            .commands
+            .iter()
+            .filter(|command| command.starts_with(prefix))
+            .cloned()
+            .collect();
+
+        candidates.sort();
+        candidates.dedup();
+
+        Ok((start, candidates))
+    }
+}
+
+impl Hinter for ReplHelper {
+    type Hint = ReplHint;
+
+    fn hint(
+        &self,
+        line: &str,
+        pos: usize,
+        _ctx: &Context<'_>,
+    ) -> Option<Self::Hint> {
+        // Only hint at the end of the line, the hint would cover the rest
+        if !self.hints || pos < line.len() {
+            return None;
+        }
+
+        hint(line, &self.commands, self.infos, self.tokenizer)
+    }
+}
+
+impl Highlighter for ReplHelper {
+    fn highlight<'l>(&self, line: &'l str, _pos: usize) -> Cow<'l, str> {
+        match &self.theme {
+            Some(theme) => Cow::Owned(highlight(
+                line,
+                &self.commands,
+                self.infos,
+                theme,
+                self.tokenizer,
+            )),
+            None => Cow::Borrowed(line),
+        }
+    }
+
+    fn highlight_hint<'h>(&self, hint: &'h str) -> Cow<'h, str> {
+        match &self.theme {
+            Some(theme) => {
+                Cow::Owned(format!("\x1b[{}m{}\x1b[0m", theme.hint, hint))
+            }
+            None => Cow::Borrowed(hint),
+        }
+    }
+
+    fn highlight_char(&self, _line: &str, _pos: usize) -> bool {
+        self.theme.is_some()
+    }
+
+    fn highlight_prompt<'b, 's: 'b, 'p: 'b>(
+        &'s self,
+        prompt: &'p str,
+        _default: bool,
+    ) -> Cow<'b, str> {
+        // Other prompts, like the one of reverse search, are left alone
+        if prompt == self.prompt.plain {
+            Cow::Borrowed(&self.prompt.styled)
+        } else {
+            Cow::Borrowed(prompt)
+        }
+    }
+}
+
+impl Validator for ReplHelper {
+    fn validate(
+        &self,
+        ctx: &mut ValidationContext,
+    ) -> rustyline::Result<ValidationResult> {
+        let validator = match &self.validator {
+            Some(validator) => validator,
+            None => return Ok(ValidationResult::Valid(None)),
+        };
+
+        // The line stays in the buffer, with the error below it
+        Ok(match validator(ctx.input()) {
+            Ok(()) => ValidationResult::Valid(None),
+            Err(err) => ValidationResult::Invalid(Some(format!("\n{:#}", err))),
+        })
+    }
+}
+
+impl Helper for ReplHelper {}
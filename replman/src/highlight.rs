@@ -0,0 +1,169 @@
+use crate::command_info::{find_command, CommandInfo};
+use crate::tokenizer::{token_spans, TokenizerConfig};
+use crate::variables;
+
+/// Colors of the input line, as ANSI SGR parameters, e.g. `"1;32"` for bold
+/// green
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Theme {
+    /// Command word of a command, builtin or alias
+    pub known_command: String,
+    /// Command word that isn't a known command
+    pub unknown_command: String,
+    /// Single and double quoted strings
+    pub quoted: String,
+    pub number: String,
+    /// Arguments starting with `-`
+    pub flag: String,
+    /// `$name` and `${name}` references
+    pub variable: String,
+    /// Hints after the cursor
+    pub hint: String,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self {
+            known_command: "32".to_string(),
+            unknown_command: "31".to_string(),
+            quoted: "33".to_string(),
+            number: "36".to_string(),
+            flag: "34".to_string(),
+            variable: "35".to_string(),
+            hint: "90".to_string(),
+        }
+    }
+}
+
+/// Colors the tokens of `line`, leaving the whitespace between them as is
+///
+/// The command word is known if it's one of `commands` or matches a
+/// `starts_with` prefix of `infos`.
+pub(crate) fn highlight(
+    line: &str,
+    commands: &[String],
+    infos: &[CommandInfo],
+    theme: &Theme,
+    config: TokenizerConfig,
+) -> String {
+    let mut ret = String::with_capacity(line.len() * 2);
+    let mut end = 0;
+
+    for (idx, span) in token_spans(line, config).enumerate() {
+        // The rest of an invalid line is left alone
+        let span = match span {
+            Ok(span) => span,
+            Err(_) => break,
+        };
+
+        ret.push_str(&line[end..span.start]);
+        let token = &line[span.clone()];
+        end = span.end;
+
+        if idx == 0 {
+            let is_known = commands.iter().any(|command| command == token)
+                || find_command(infos, token).is_some();
+            let style = if is_known {
+                &theme.known_command
+            } else {
+                &theme.unknown_command
+            };
+
+            paint(&mut ret, token, style);
+        } else {
+            highlight_argument(&mut ret, token, theme);
+        }
+    }
+
+    ret.push_str(&line[end..]);
+
+    ret
+}
+
+fn highlight_argument(ret: &mut String, token: &str, theme: &Theme) {
+    let unsigned = token.trim_start_matches(['-', '+']);
+
+    if token.starts_with('\'') {
+        // Variables aren't expanded in single quotes
+        paint(ret, token, &theme.quoted);
+    } else if token.starts_with('"') {
+        highlight_variables(ret, token, Some(&theme.quoted), theme);
+    } else if unsigned.starts_with(|c: char| c.is_ascii_digit())
+        && token.parse::<f64>().is_ok()
+    {
+        paint(ret, token, &theme.number);
+    } else if token.starts_with('-') {
+        paint(ret, token, &theme.flag);
+    } else {
+        highlight_variables(ret, token, None, theme);
+    }
+}
+
+/// Paints the variable references in `token` and the rest with `style`
+fn highlight_variables(
+    ret: &mut String,
+    token: &str,
+    style: Option<&str>,
+    theme: &Theme,
+) {
+    let mut end = 0;
+    let paint_rest = |ret: &mut String, s: &str| match style {
+        Some(style) => paint(ret, s, style),
+        None => ret.push_str(s),
+    };
+
+    for reference in variables::references(token) {
+        paint_rest(ret, &token[end..reference.start]);
+        paint(ret, &token[reference.clone()], &theme.variable);
+        end = reference.end;
+    }
+
+    paint_rest(ret, &token[end..]);
+}
+
+fn paint(ret: &mut String, s: &str, style: &str) {
+    if !s.is_empty() {
+        ret.push_str(&format!("\x1b[{}m{}\x1b[0m", style, s));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use test_case::test_case;
+
+    use super::*;
+
+    #[test_case("set x 1", "<32>set</> x <36>1</>" ; "Known command")]
+    #[test_case("  sett  x", "  <31>sett</>  x" ; "Unknown command")]
+    #[test_case("qu", "<32>qu</>" ; "Prefix of starts_with")]
+    #[test_case(
+        "set 'a $b' \"$c d\"",
+        "<32>set</> <33>'a $b'</> <33>\"</><35>$c</><33> d\"</>" ;
+        "Quotes"
+    )]
+    #[test_case("set -v -1.5 -", "<32>set</> <34>-v</> <36>-1.5</> <34>-</>" ; "Flags and numbers")]
+    #[test_case("set ${a}b", "<32>set</> <35>${a}</>b" ; "Variables")]
+    #[test_case("set \"a\"b c", "<32>set</> \"a\"b c" ; "Invalid")]
+    fn highlighted(line: &str, exp: &str) {
+        let commands = vec!["set".to_string(), "quit".to_string()];
+        let infos = &[CommandInfo {
+            name: "quit",
+            aliases: &[],
+            starts_with: &["q"],
+            args: &[],
+        }];
+        let actual = highlight(
+            line,
+            &commands,
+            infos,
+            &Theme::default(),
+            Default::default(),
+        );
+
+        let actual = (31..=36)
+            .fold(actual.replace("\x1b[0m", "</>"), |s, c| {
+                s.replace(&format!("\x1b[{}m", c), &format!("<{}>", c))
+            });
+        assert_eq!(actual, exp);
+    }
+}
@@ -0,0 +1,158 @@
+use rustyline::hint::Hint;
+
+use crate::command_info::CommandInfo;
+use crate::tokenizer::{token_spans, TokenizerConfig};
+
+/// Greyed out text after the cursor
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct ReplHint {
+    display: String,
+    /// Inserted when the hint is accepted, the rest of the command name
+    completion: Option<String>,
+}
+
+impl Hint for ReplHint {
+    fn display(&self) -> &str {
+        &self.display
+    }
+
+    fn completion(&self) -> Option<&str> {
+        self.completion.as_deref()
+    }
+}
+
+/// Hints the rest of the command name and the remaining arguments of the
+/// command being typed at the end of `line`
+pub(crate) fn hint(
+    line: &str,
+    commands: &[String],
+    infos: &[CommandInfo],
+    config: TokenizerConfig,
+) -> Option<ReplHint> {
+    let spans = token_spans(line, config)
+        .collect::<anyhow::Result<Vec<_>>>()
+        .ok()?;
+    let cmd_word = &line[spans.first()?.clone()];
+    let typing_token = spans.last()?.end == line.len();
+
+    let is_exact = commands.iter().any(|command| command == cmd_word);
+
+    let (completion, name) = if spans.len() == 1 && typing_token && !is_exact {
+        let mut candidates = commands
+            .iter()
+            .filter(|command| command.starts_with(cmd_word));
+
+        match (candidates.next(), candidates.next()) {
+            (Some(command), None) => {
+                (command[cmd_word.len()..].to_string(), command.as_str())
+            }
+            _ => return None,
+        }
+    } else {
+        (String::new(), cmd_word)
+    };
+
+    let info = infos.iter().find(|info| info.is_named(name));
+    let typed_args = spans.len() - 1;
+
+    let args: Vec<_> = match info {
+        // Everything after the command word is the argument of a raw command
+        Some(info) if info.is_raw() && typed_args > 0 => {
+            vec![]
+        }
+        Some(info) => info
+            .args
+            .iter()
+            .skip(typed_args)
+            .map(ToString::to_string)
+            .collect(),
+        None => vec![],
+    };
+
+    let mut display = completion.clone();
+    if !args.is_empty() {
+        if typing_token {
+            display.push(' ');
+        }
+        display.push_str(&args.join(" "));
+    }
+
+    if display.is_empty() {
+        return None;
+    }
+
+    Some(ReplHint {
+        display,
+        completion: Some(completion)
+            .filter(|completion| !completion.is_empty()),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use test_case::test_case;
+
+    use super::*;
+    use crate::command_info::ArgInfo;
+
+    const INFOS: &[CommandInfo] = &[
+        CommandInfo {
+            name: "named_args",
+            aliases: &["na"],
+            starts_with: &[],
+            args: &[
+                ArgInfo {
+                    name: "left",
+                    ty: "usize",
+                    optional: false,
+                    raw: false,
+                },
+                ArgInfo {
+                    name: "right",
+                    ty: "usize",
+                    optional: true,
+                    raw: false,
+                },
+            ],
+        },
+        CommandInfo {
+            name: "eval",
+            aliases: &[],
+            starts_with: &[],
+            args: &[ArgInfo {
+                name: "0",
+                ty: "String",
+                optional: false,
+                raw: true,
+            }],
+        },
+    ];
+
+    #[test_case("named_args 1", Some((" [right: usize]", None)) ; "After an argument")]
+    #[test_case("named_args ", Some(("<left: usize> [right: usize]", None)) ; "After a space")]
+    #[test_case("na 1 ", Some(("[right: usize]", None)) ; "Alias")]
+    #[test_case("na", Some((" <left: usize> [right: usize]", None)) ; "Prefix of another command")]
+    #[test_case("named_args 1 2", None ; "All arguments")]
+    #[test_case("nam", Some(("ed_args <left: usize> [right: usize]", Some("ed_args"))) ; "Partial name")]
+    #[test_case("n", None ; "Ambiguous name")]
+    #[test_case("ev", Some(("al <0...>", Some("al"))) ; "Raw")]
+    #[test_case("eval 1 +", None ; "Raw argument")]
+    #[test_case("set", None ; "Without info")]
+    #[test_case("", None ; "Empty")]
+    fn hints(line: &str, exp: Option<(&str, Option<&str>)>) {
+        let commands: Vec<_> = ["named_args", "na", "eval", "set"]
+            .iter()
+            .map(ToString::to_string)
+            .collect();
+
+        let actual = hint(line, &commands, INFOS, Default::default());
+
+        assert_eq!(
+            actual,
+            exp.map(|(display, completion)| ReplHint {
+                display: display.to_string(),
+                completion: completion.map(ToString::to_string),
+            })
+        );
+    }
+}
@@ -0,0 +1,51 @@
+/// Resolves `!n` (the n-th entry, starting at 1) and `!!` (the last entry)
+///
+/// Returns `None` if the line isn't a history reference.
+pub(crate) fn recall(
+    history: &[String],
+    line: &str,
+) -> anyhow::Result<Option<String>> {
+    let reference = match line.strip_prefix('!') {
+        Some(reference) => reference,
+        None => return Ok(None),
+    };
+
+    let entry = if reference == "!" {
+        history.last()
+    } else if let Ok(n) = reference.parse::<usize>() {
+        n.checked_sub(1).and_then(|idx| history.get(idx))
+    } else {
+        return Ok(None);
+    };
+
+    entry
+        .cloned()
+        .map(Some)
+        .ok_or_else(|| anyhow::anyhow!("No such history entry '{}'", line))
+}
+
+#[cfg(test)]
+mod tests {
+    use test_case::test_case;
+
+    use super::*;
+
+    fn history() -> Vec<String> {
+        vec!["first".to_string(), "second".to_string()]
+    }
+
+    #[test_case("!1", Some("first") ; "First")]
+    #[test_case("!2", Some("second") ; "Second")]
+    #[test_case("!!", Some("second") ; "Last")]
+    #[test_case("!foo", None ; "Not a reference")]
+    #[test_case("history", None ; "Not prefixed")]
+    fn recalls(line: &str, exp: Option<&str>) {
+        assert_eq!(recall(&history(), line).unwrap().as_deref(), exp);
+    }
+
+    #[test_case("!0" ; "Zero")]
+    #[test_case("!3" ; "Out of bounds")]
+    fn missing_entry(line: &str) {
+        assert!(recall(&history(), line).is_err());
+    }
+}
@@ -1,63 +1,1264 @@
+use std::cell::RefCell;
+use std::io::{self, BufRead, IsTerminal, Write};
+use std::path::{Path, PathBuf};
 use std::str::FromStr;
+use std::sync::{Arc, Mutex, MutexGuard};
 
-use rustyline::Editor;
+pub use aliases::Aliases;
+use anyhow::Context;
+#[cfg(feature = "tokio")]
+pub use async_repl::AsyncReplHandler;
+pub use builder::{
+    BellStyle, EditMode, HistoryPolicy, InterruptBehavior, ReplBuilder,
+};
+pub use command_info::{ArgInfo, CommandInfo};
+pub use handler::{ControlFlow, ReplHandler};
+pub use highlight::Theme;
+pub use line_source::{
+    BufReadSource, LineContext, LineSource, LineValidator, PrintHook, ReadLine,
+    ScriptedSource,
+};
+pub use outcome::ReadOutcome;
+pub use output::Output;
+pub use printer::ReplPrinter;
+pub use prompt::Prompt;
+use recording::{RecordKind, Recording};
+#[cfg(feature = "rustyline")]
+pub use rustyline_source::RustylineSource;
+use script::Script;
+pub use tokenizer::{quote_arg, TokenizerConfig};
+use tokenizer::{
+    split_redirect, split_string_expand, split_string_unescape, Redirect,
+};
+pub use variables::Variables;
+
+mod aliases;
+#[cfg(feature = "tokio")]
+mod async_repl;
+mod builder;
+mod command_info;
+#[doc(hidden)]
+pub mod command_line;
+mod handler;
+#[cfg(feature = "rustyline")]
+mod helper;
+// Only the rustyline helper highlights the input line
+#[cfg_attr(not(feature = "rustyline"), allow(dead_code))]
+mod highlight;
+#[cfg(feature = "rustyline")]
+mod hint;
+mod history;
+mod line_source;
+mod outcome;
+mod output;
+mod printer;
+mod prompt;
+mod recording;
+#[cfg(feature = "rustyline")]
+mod rustyline_source;
+mod script;
+#[cfg(feature = "serde")]
+pub mod serde;
+pub mod testing;
+mod tokenizer;
+mod variables;
+
+/// Commands handled by [`Repl`] itself, before the line reaches
+/// [`ReplCmd::parse`]
+///
+/// A builtin is only provided if the [`ReplCmd`] doesn't define a command
+/// with the same name.
+const BUILTINS: &[&str] = &[
+    "alias", "exit", "help", "history", "quit", "set", "source", "unalias",
+];
 
 pub mod prelude {
     pub use replman_derive::ReplCmd;
 
-    pub use crate::{read_command, Repl, ReplCmd};
+    pub use crate::{
+        read_command, ControlFlow, EditMode, ReadOutcome, Repl, ReplBuilder,
+        ReplCmd, ReplHandler, ToCommandLine,
+    };
+}
+
+type ErrorPrinter = Box<dyn Fn(&anyhow::Error) + Send>;
+
+/// A line after the builtins were handled
+enum Processed<R> {
+    Command(R, Output),
+    Builtin,
+    Quit,
+}
+
+/// How `| cmd` and `> file` at the end of a line are handled
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Redirects {
+    /// Pipes are disabled, `|` and `>` are passed to the command
+    Disabled,
+    /// The output of builtins and commands is redirected
+    Enabled,
+    /// The caller doesn't take the output of commands, only builtins are
+    /// redirected
+    BuiltinsOnly,
+}
+
+enum PromptSource {
+    Static(Prompt),
+    Provider(Box<dyn FnMut() -> Prompt + Send>),
 }
 
 pub struct Repl {
-    editor: Editor<()>,
+    /// Shared with the reader thread of the async API
+    source: Arc<Mutex<Box<dyn LineSource>>>,
+    prompt: PromptSource,
+    interrupt_behavior: InterruptBehavior,
+    /// The previous line was interrupted, for
+    /// [`InterruptBehavior::ConfirmExit`]
+    interrupted: bool,
+    variables: Variables,
+    aliases: Aliases,
+    pipes: bool,
+    tokenizer: TokenizerConfig,
+    history_file: Option<PathBuf>,
+    history_ignore_space: bool,
+    history_policy: HistoryPolicy,
+    retry_failed_line: bool,
+    /// The line that failed, to be shown in the next prompt
+    failed_line: Option<String>,
+    skip_blank_lines: bool,
+    /// Colors of the input line, `None` if highlighting is disabled
+    theme: Option<Theme>,
+    hints: bool,
+    error_printer: ErrorPrinter,
+    printer: ReplPrinter,
+    /// Write the output of commands with the printer instead of stdout, so
+    /// that it can be captured
+    print_output: bool,
+    /// A session is being recorded, see [`ReplBuilder::record`]
+    recording: bool,
+    /// Scripts lines are read from before the line source, the innermost
+    /// last
+    scripts: Vec<Script>,
+    /// Read from the line source once the scripts are done, otherwise that's
+    /// the end of input
+    interactive: bool,
+    stop_on_error: bool,
+    echo: bool,
+    validate_input: bool,
+    #[cfg(feature = "tokio")]
+    reader: Option<async_repl::Reader>,
 }
 
 impl Repl {
+    /// Creates a repl reading from the terminal
+    ///
+    /// If stdin isn't a terminal, e.g. `cat commands | app`, commands are
+    /// read from it as a script instead, see [`Repl::from_reader`].
+    ///
+    /// Without the `rustyline` feature, lines are read from stdin without
+    /// editing, see [`BufReadSource`].
+    ///
+    /// # Panics
+    ///
+    /// If the terminal can't be set up, use [`Repl::builder`] to handle the
+    /// error instead
     #[allow(clippy::new_without_default)]
     pub fn new() -> Self {
+        Self::builder()
+            .build()
+            .expect("Failed to create the editor")
+    }
+
+    /// Creates a repl that runs the commands of a script, one per line,
+    /// instead of reading from the terminal
+    ///
+    /// `name` is used to report errors as `name:line`. Lines starting with
+    /// `#` are comments. Reading stops with an error at the first line that
+    /// fails, use [`ReplBuilder::stop_on_error`] to continue instead.
+    ///
+    /// ```no_run
+    /// # use std::fs::File;
+    /// # use std::io::BufReader;
+    /// # use replman::prelude::*;
+    /// let file = BufReader::new(File::open("setup.repl")?);
+    /// let repl = Repl::from_reader(file, "setup.repl");
+    /// # Ok::<(), anyhow::Error>(())
+    /// ```
+    pub fn from_reader(
+        reader: impl BufRead + Send + 'static,
+        name: impl Into<String>,
+    ) -> Self {
+        // Without a history file or recording, building can't fail
+        Self::builder()
+            .script(reader, name)
+            .build()
+            .expect("Failed to create the repl")
+    }
+
+    pub fn builder() -> ReplBuilder {
+        ReplBuilder::new()
+    }
+
+    fn with_source(mut source: Box<dyn LineSource>) -> Self {
+        let printer = source.external_printer();
+
         Self {
-            editor: Editor::new(),
+            source: Arc::new(Mutex::new(source)),
+            prompt: PromptSource::Static(Prompt::new("> ")),
+            interrupt_behavior: InterruptBehavior::ClearLine,
+            interrupted: false,
+            variables: Variables::default(),
+            aliases: Aliases::default(),
+            pipes: false,
+            tokenizer: TokenizerConfig::default(),
+            history_file: None,
+            history_ignore_space: false,
+            history_policy: HistoryPolicy::Successful,
+            retry_failed_line: false,
+            failed_line: None,
+            skip_blank_lines: true,
+            theme: io::stdout().is_terminal().then(Theme::default),
+            hints: true,
+            error_printer: Box::new(builder::default_error_printer),
+            printer: ReplPrinter::new(printer),
+            print_output: false,
+            recording: false,
+            scripts: vec![],
+            interactive: true,
+            stop_on_error: true,
+            echo: false,
+            validate_input: false,
+            #[cfg(feature = "tokio")]
+            reader: None,
+        }
+    }
+
+    /// Reads from `script` instead of the terminal
+    fn set_script(&mut self, script: Script) {
+        self.scripts = vec![script];
+        self.interactive = false;
+    }
+
+    pub fn set_prompt(&mut self, prompt: impl Into<Prompt>) {
+        self.prompt = PromptSource::Static(prompt.into());
+    }
+
+    /// Sets a closure that's called for a fresh prompt before every line
+    pub fn set_prompt_provider<P>(
+        &mut self,
+        mut provider: impl FnMut() -> P + Send + 'static,
+    ) where
+        P: Into<Prompt>,
+    {
+        self.prompt =
+            PromptSource::Provider(Box::new(move || provider().into()));
+    }
+
+    /// Sets what happens when the user presses Ctrl-C, clears the line by
+    /// default
+    pub fn set_interrupt_behavior(&mut self, behavior: InterruptBehavior) {
+        self.interrupt_behavior = behavior;
+    }
+
+    /// Sets which lines are recorded in the history, the successful ones by
+    /// default
+    pub fn set_history_policy(&mut self, policy: HistoryPolicy) {
+        self.history_policy = policy;
+    }
+
+    pub fn set_tokenizer_config(&mut self, config: TokenizerConfig) {
+        self.tokenizer = config;
+    }
+
+    /// Sets the colors of the input line, `None` disables highlighting
+    ///
+    /// Highlighting is always disabled if stdout isn't a terminal.
+    pub fn set_theme(&mut self, theme: Option<Theme>) {
+        self.theme = theme.filter(|_| io::stdout().is_terminal());
+    }
+
+    /// Enables shell pipes (`cmd | grep foo`) and output redirection
+    /// (`cmd > file`, `cmd >> file`)
+    ///
+    /// Commands are only redirected by [`Repl::execute`] and
+    /// [`Repl::read_command_with_output`], as the command has to write its
+    /// output to the provided [`Output`]. Other reads, like [`Repl::run`],
+    /// reject redirected commands with an error, builtins are redirected
+    /// either way. The output of `#[replman(raw)]` commands can't be
+    /// redirected, `|` and `>` are passed to them as is.
+    pub fn set_pipes_enabled(&mut self, enabled: bool) {
+        self.pipes = enabled;
+    }
+
+    /// A handle to print messages from background threads or tasks while
+    /// the user is typing, see [`ReplPrinter`]
+    pub fn printer(&self) -> ReplPrinter {
+        self.printer.clone()
+    }
+
+    /// Records the session to the file at `path`, see [`ReplBuilder::record`]
+    pub fn start_recording(
+        &mut self,
+        path: impl AsRef<Path>,
+    ) -> anyhow::Result<()> {
+        let recording = Recording::create(path.as_ref())?;
+        self.printer.set_recording(Some(recording));
+        self.recording = true;
+
+        Ok(())
+    }
+
+    pub fn stop_recording(&mut self) {
+        self.printer.set_recording(None);
+        self.recording = false;
+    }
+
+    pub fn variables(&self) -> &Variables {
+        &self.variables
+    }
+
+    pub fn variables_mut(&mut self) -> &mut Variables {
+        &mut self.variables
+    }
+
+    /// Sets a variable that can be referenced as `$name` or `${name}`
+    pub fn set_var(
+        &mut self,
+        name: impl Into<String>,
+        value: impl Into<String>,
+    ) {
+        self.variables.set(name, value);
+    }
+
+    pub fn aliases(&self) -> &Aliases {
+        &self.aliases
+    }
+
+    pub fn aliases_mut(&mut self) -> &mut Aliases {
+        &mut self.aliases
+    }
+
+    /// Defines an alias, as if the user typed `alias name = expansion`
+    pub fn add_alias(
+        &mut self,
+        name: impl Into<String>,
+        expansion: impl Into<String>,
+    ) {
+        self.aliases.set(name, expansion);
+    }
+
+    /// Help for `R` followed by the builtins and the runtime aliases
+    pub fn help<R>(&self) -> String
+    where
+        R: ReplCmd,
+    {
+        let mut help = R::help().to_string();
+
+        if !is_defined::<R>("help") {
+            help.push_str("help - Displays help\n");
+        }
+
+        if !is_defined::<R>("quit") && !is_defined::<R>("exit") {
+            help.push_str("quit|exit - Exits the program\n");
+        }
+
+        let builtins = [
+            ("set", "set <name> <value> - Sets a variable\n"),
+            ("alias", "alias <name> = <command> - Defines an alias\n"),
+            ("unalias", "unalias <name> - Removes an alias\n"),
+            (
+                "history",
+                "history - Lists previous commands, recall with !n\n",
+            ),
+            ("source", "source <file> - Runs the commands in a file\n"),
+        ];
+        for (name, line) in builtins {
+            if !is_defined::<R>(name) {
+                help.push_str(line);
+            }
+        }
+
+        if !self.aliases.is_empty() {
+            help.push_str("\nAliases:\n");
+            for (name, expansion) in self.aliases.iter() {
+                help.push_str(&format!("{} = {}\n", name, expansion));
+            }
+        }
+
+        help
+    }
+
+    /// Reads lines until one is a command or the input ends
+    ///
+    /// Errors of lines that couldn't be parsed are printed and the next line
+    /// is read, only I/O errors are returned.
+    pub fn read_command<R>(&mut self) -> anyhow::Result<ReadOutcome<R>>
+    where
+        R: ReplCmd,
+    {
+        Ok(self.read(self.redirects(false))?.map(|(cmd, _)| cmd))
+    }
+
+    /// Reads a command along with the [`Output`] it should write to
+    ///
+    /// If pipes are enabled, the output is connected to the shell pipeline
+    /// or file the user redirected the command to, otherwise it's stdout.
+    pub fn read_command_with_output<R>(
+        &mut self,
+    ) -> anyhow::Result<ReadOutcome<(R, Output)>>
+    where
+        R: ReplCmd,
+    {
+        self.read(self.redirects(true))
+    }
+
+    /// Reads a command and executes it with `f`, writing to the [`Output`]
+    ///
+    /// Waits for the shell pipeline, if any, to finish before returning.
+    pub fn execute<R, F, T>(&mut self, f: F) -> anyhow::Result<ReadOutcome<T>>
+    where
+        R: ReplCmd,
+        F: FnOnce(R, &mut Output) -> anyhow::Result<T>,
+    {
+        let (cmd, mut output) = match self.read_command_with_output()? {
+            ReadOutcome::Command(read) => read,
+            ReadOutcome::Interrupted => return Ok(ReadOutcome::Interrupted),
+            ReadOutcome::Eof => return Ok(ReadOutcome::Eof),
+        };
+
+        let ret = f(cmd, &mut output);
+        output.finish()?;
+
+        ret.map(ReadOutcome::Command)
+    }
+
+    /// Reads and handles commands until the handler or the user exits
+    ///
+    /// Provides `help` and `quit`/`exit` unless `R` defines them. Errors of
+    /// the handler are printed and don't end the session. Ctrl-D exits, or
+    /// passes the `#[replman(on_eof)]` variant to the handler if `R` has one.
+    pub fn run<R, H>(&mut self, handler: &mut H) -> anyhow::Result<()>
+    where
+        R: ReplCmd,
+        H: ReplHandler<R>,
+    {
+        loop {
+            let cmd = match self.read_command::<R>()? {
+                ReadOutcome::Command(cmd) => cmd,
+                ReadOutcome::Interrupted => continue,
+                ReadOutcome::Eof => return Ok(()),
+            };
+
+            match handler.handle(cmd) {
+                Ok(ControlFlow::Continue) => (),
+                Ok(ControlFlow::Exit) => return Ok(()),
+                Err(err) => self.report_error(err)?,
+            }
+        }
+    }
+
+    /// Runs the inputs of a session recorded with [`ReplBuilder::record`]
+    /// through `handler`, as if they were typed again
+    ///
+    /// Errors are printed and the next input is replayed, it stops once the
+    /// handler or an input exits. Scripts that are being read are paused.
+    ///
+    /// The lines of scripts are recorded as inputs too, so scripts opened by
+    /// a replayed `source` aren't read again, their lines are replayed from
+    /// the recording instead.
+    pub fn replay<R, H>(
+        &mut self,
+        path: impl AsRef<Path>,
+        handler: &mut H,
+    ) -> anyhow::Result<()>
+    where
+        R: ReplCmd,
+        H: ReplHandler<R>,
+    {
+        let inputs = recording::read_inputs(path.as_ref())?;
+
+        let scripts = std::mem::take(&mut self.scripts);
+        let ret = self.replay_inputs(inputs, handler);
+        self.scripts = scripts;
+
+        ret
+    }
+
+    fn replay_inputs<R, H>(
+        &mut self,
+        inputs: Vec<String>,
+        handler: &mut H,
+    ) -> anyhow::Result<()>
+    where
+        R: ReplCmd,
+        H: ReplHandler<R>,
+    {
+        for input in inputs {
+            let redirects = self.redirects(false);
+            let read = self.handle_line::<R>(&input, redirects);
+            self.scripts.clear();
+
+            let cmd = match read {
+                Some(read) => read?,
+                None => continue,
+            };
+
+            let cmd = match cmd {
+                ReadOutcome::Command((cmd, _)) => cmd,
+                ReadOutcome::Interrupted => continue,
+                ReadOutcome::Eof => return Ok(()),
+            };
+
+            match handler.handle(cmd) {
+                Ok(ControlFlow::Continue) => (),
+                Ok(ControlFlow::Exit) => return Ok(()),
+                Err(err) => self.report_error(err)?,
+            }
         }
+
+        Ok(())
     }
 
-    pub fn read_command<R>(&mut self) -> anyhow::Result<R>
+    fn read<R>(
+        &mut self,
+        redirects: Redirects,
+    ) -> anyhow::Result<ReadOutcome<(R, Output)>>
     where
         R: ReplCmd,
     {
         loop {
-            let line = self.editor.readline("> ")?;
-            let trimmed = line.trim();
+            let line = match self.read_script_line() {
+                Some(line) => line,
+                None => {
+                    self.update_context::<R>(redirects);
+                    let prompt = self.next_prompt();
+                    let initial = self.failed_line.take();
+
+                    self.source().read_line(&prompt, initial.as_deref())
+                }
+            };
+
+            if let Some(ret) = self.handle_read(line, redirects) {
+                return ret;
+            }
+        }
+    }
+
+    /// Reads the next line of the current script, `None` if the line source
+    /// should be read from
+    fn read_script_line(&mut self) -> Option<io::Result<ReadLine>> {
+        while let Some(script) = self.scripts.last_mut() {
+            let read = script.read_line();
+            if let Some(line) = self.handle_script_read(read) {
+                return Some(line);
+            }
+        }
+
+        if self.interactive {
+            None
+        } else {
+            Some(Ok(ReadLine::Eof))
+        }
+    }
+
+    /// Handles the result of reading from the innermost script
+    ///
+    /// Returns `None` if the script ended and the next one should be read.
+    fn handle_script_read(
+        &mut self,
+        read: io::Result<Option<String>>,
+    ) -> Option<io::Result<ReadLine>> {
+        match read {
+            Ok(Some(line)) => {
+                let startup = self.scripts.last().is_some_and(|s| s.startup);
+                if self.echo && !startup {
+                    let prompt = self.next_prompt().render(None).plain;
+                    let echoed = self.printer.println(prompt + &line);
+                    if let Err(err) = echoed {
+                        self.scripts.clear();
+                        return Some(Err(io::Error::other(err)));
+                    }
+                }
+
+                Some(Ok(ReadLine::Line(line)))
+            }
+            Ok(None) => {
+                self.scripts.pop();
+                None
+            }
+            Err(err) => {
+                self.scripts.clear();
+                Some(Err(err))
+            }
+        }
+    }
+
+    /// Prints the error of a line, or returns it if a script should stop
+    ///
+    /// Errors from scripts are prefixed with the `file:line` they happened
+    /// at, followed by the lines the script was sourced from. Errors from
+    /// startup files are always returned.
+    fn report_error(&mut self, err: anyhow::Error) -> anyhow::Result<()> {
+        if self.scripts.is_empty() {
+            self.print_error(&err);
+            return Ok(());
+        }
+
+        // A startup file isn't sourced from the script below it
+        let startup = self.scripts.iter().rposition(|script| script.startup);
+        let locations: Vec<_> = self.scripts[startup.unwrap_or(0)..]
+            .iter()
+            .rev()
+            .map(Script::location)
+            .collect();
+        let err = err.context(locations.join(", sourced from "));
+
+        if !self.stop_on_error && startup.is_none() {
+            self.print_error(&err);
+            return Ok(());
+        }
+
+        self.scripts.clear();
+
+        if self.interactive && startup.is_none() {
+            self.print_error(&err);
+            Ok(())
+        } else {
+            Err(err)
+        }
+    }
+
+    fn print_error(&self, err: &anyhow::Error) {
+        // There's nowhere left to report a failure to record the error
+        let _ = self
+            .printer
+            .record(RecordKind::Error, &format!("{:#}", err));
+        (self.error_printer)(err);
+    }
+
+    /// How redirects are handled, depending on whether the caller takes
+    /// the [`Output`] of commands
+    fn redirects(&self, with_output: bool) -> Redirects {
+        match (self.pipes, with_output) {
+            (false, _) => Redirects::Disabled,
+            (true, true) => Redirects::Enabled,
+            (true, false) => Redirects::BuiltinsOnly,
+        }
+    }
+
+    fn source(&self) -> MutexGuard<'_, Box<dyn LineSource>> {
+        self.source.lock().expect("Line source lock poisoned")
+    }
+
+    /// Handles the result of reading a line from the line source
+    ///
+    /// Returns `None` if the next line should be read.
+    fn handle_read<R>(
+        &mut self,
+        line: io::Result<ReadLine>,
+        redirects: Redirects,
+    ) -> Option<anyhow::Result<ReadOutcome<(R, Output)>>>
+    where
+        R: ReplCmd,
+    {
+        let interrupted = std::mem::take(&mut self.interrupted);
+
+        let line = match line {
+            Ok(ReadLine::Line(line)) => line,
+            Ok(ReadLine::Interrupted) => {
+                return match self.interrupt_behavior {
+                    InterruptBehavior::ClearLine => None,
+                    InterruptBehavior::ConfirmExit if interrupted => {
+                        Some(self.eof())
+                    }
+                    InterruptBehavior::ConfirmExit => {
+                        self.interrupted = true;
+                        self.printer
+                            .println("Press Ctrl-C again to exit")
+                            .err()
+                            .map(Err)
+                    }
+                    InterruptBehavior::Exit => Some(self.eof()),
+                    InterruptBehavior::Return => {
+                        Some(Ok(ReadOutcome::Interrupted))
+                    }
+                };
+            }
+            Ok(ReadLine::Eof) => return Some(self.eof()),
+            Err(err) => return Some(Err(err.into())),
+        };
+
+        self.handle_line(&line, redirects)
+    }
+
+    /// The end of input, as the `on_eof` variant of `R` if it has one
+    fn eof<R>(&self) -> anyhow::Result<ReadOutcome<(R, Output)>>
+    where
+        R: ReplCmd,
+    {
+        match R::on_eof() {
+            Some(cmd) => {
+                self.record_command(&cmd)?;
+                Ok(ReadOutcome::Command((cmd, self.stdout())))
+            }
+            None => Ok(ReadOutcome::Eof),
+        }
+    }
+
+    /// Records `cmd` if a session is being recorded
+    fn record_command<R>(&self, cmd: &R) -> io::Result<()>
+    where
+        R: ReplCmd,
+    {
+        if !self.recording {
+            return Ok(());
+        }
+
+        match cmd.debug_record() {
+            Some(text) => self.printer.record(RecordKind::Command, &text),
+            None => Ok(()),
+        }
+    }
+
+    /// Handles a line read from a script or the line source
+    ///
+    /// Returns `None` if the line didn't result in a command and the next
+    /// line should be read.
+    fn handle_line<R>(
+        &mut self,
+        line: &str,
+        redirects: Redirects,
+    ) -> Option<anyhow::Result<ReadOutcome<(R, Output)>>>
+    where
+        R: ReplCmd,
+    {
+        if let Err(err) = self.printer.record(RecordKind::Input, line) {
+            return Some(Err(err.into()));
+        }
+
+        let trimmed = line.trim();
+
+        if trimmed.is_empty() && self.skip_blank_lines {
+            return None;
+        }
+
+        // Line sources return a copy of their history, only made for lines
+        // that reference it
+        let recalled = if trimmed.starts_with('!') {
+            history::recall(&self.source().history(), trimmed)
+        } else {
+            Ok(None)
+        };
+        let recalled = match recalled {
+            Ok(recalled) => recalled,
+            Err(err) => return self.report_error(err).err().map(Err),
+        };
+
+        let trimmed = match &recalled {
+            Some(recalled) => {
+                if let Err(err) = self.printer.println(recalled) {
+                    return Some(Err(err));
+                }
+
+                recalled.as_str()
+            }
+            None => trimmed,
+        };
+
+        // Lines of scripts don't end up in the history
+        let interactive = self.scripts.is_empty();
+        let ignored = trimmed.is_empty()
+            || !interactive
+            || (self.history_ignore_space && line.starts_with(' '));
+
+        match self.process_line(trimmed, redirects) {
+            Ok(processed) => {
+                if self.history_policy != HistoryPolicy::None && !ignored {
+                    self.add_history_entry(trimmed);
+                }
+
+                match processed {
+                    Processed::Command(cmd, output) => {
+                        if let Err(err) = self.record_command(&cmd) {
+                            return Some(Err(err.into()));
+                        }
 
-            if trimmed.is_empty() {
-                continue;
+                        Some(Ok(ReadOutcome::Command((cmd, output))))
+                    }
+                    Processed::Builtin => None,
+                    // Quitting is the same as ending the input
+                    Processed::Quit => Some(self.eof()),
+                }
             }
+            Err(err) => {
+                if self.history_policy == HistoryPolicy::All && !ignored {
+                    self.add_history_entry(trimmed);
+                }
 
-            match R::parse(split_string_unescape(trimmed)) {
-                Ok(cmd) => {
-                    self.editor.add_history_entry(trimmed);
-                    return Ok(cmd);
+                if self.retry_failed_line && interactive {
+                    self.failed_line = Some(trimmed.to_string());
                 }
-                Err(err) => eprintln!("Failed to parse command: {}", err),
+
+                self.report_error(err).err().map(Err)
             }
         }
     }
+
+    /// Parses a line into a command, handling the builtin commands
+    fn process_line<R>(
+        &mut self,
+        line: &str,
+        redirects: Redirects,
+    ) -> anyhow::Result<Processed<R>>
+    where
+        R: ReplCmd,
+    {
+        let line = self.tokenizer.normalize(line);
+        let line = self.aliases.expand(&line);
+        let (line, redirect) = if redirects != Redirects::Disabled {
+            split_command_redirect::<R>(&line)
+        } else {
+            (line.as_str(), None)
+        };
+
+        match aliases::split_cmd_word(line) {
+            // Commands defined by `R` take precedence over builtins
+            (cmd, _) if is_defined::<R>(cmd) => {}
+            ("help", _) => {
+                let mut output = self.open_output(redirect)?;
+                write!(output, "{}", self.help::<R>())?;
+                output.finish()?;
+                return Ok(Processed::Builtin);
+            }
+            ("quit" | "exit", _) => return Ok(Processed::Quit),
+            ("alias", args) => {
+                let mut output = self.open_output(redirect)?;
+                self.builtin_alias(args.trim(), &mut output)?;
+                output.finish()?;
+                return Ok(Processed::Builtin);
+            }
+            ("unalias", args) => {
+                self.builtin_unalias(args.trim())?;
+                return Ok(Processed::Builtin);
+            }
+            ("history", _) => {
+                let mut output = self.open_output(redirect)?;
+                let history = self.source().history();
+                for (idx, entry) in history.iter().enumerate() {
+                    writeln!(output, "{:>5}  {}", idx + 1, entry)?;
+                }
+                output.finish()?;
+                return Ok(Processed::Builtin);
+            }
+            ("source", args) => {
+                let paths =
+                    split_string_expand(args, &self.variables, self.tokenizer)
+                        .collect::<anyhow::Result<Vec<_>>>()?;
+                let path = match paths.as_slice() {
+                    [path] => PathBuf::from(path),
+                    _ => anyhow::bail!("Usage: source <file>"),
+                };
+
+                self.builtin_source(&path)?;
+                return Ok(Processed::Builtin);
+            }
+            ("set", args) => {
+                let args =
+                    split_string_expand(args, &self.variables, self.tokenizer)
+                        .collect::<anyhow::Result<Vec<_>>>()?;
+                let mut output = self.open_output(redirect)?;
+                self.builtin_set(&args, &mut output)?;
+                output.finish()?;
+                return Ok(Processed::Builtin);
+            }
+            _ => (),
+        }
+
+        // Tokens are expanded lazily, so that raw commands never fail on
+        // variable references or quoting in their arguments
+        let parts = split_string_expand(line, &self.variables, self.tokenizer);
+        let cmd = R::parse(line, parts).context("Failed to parse command")?;
+        if redirects == Redirects::BuiltinsOnly && redirect.is_some() {
+            anyhow::bail!(REDIRECT_UNSUPPORTED);
+        }
+        let output = self.open_output(redirect)?;
+
+        Ok(Processed::Command(cmd, output))
+    }
+
+    /// The prompt for the next line, from the provider if there is one
+    fn next_prompt(&mut self) -> Prompt {
+        match &mut self.prompt {
+            PromptSource::Static(prompt) => prompt.clone(),
+            PromptSource::Provider(provider) => provider(),
+        }
+    }
+
+    fn add_history_entry(&mut self, line: &str) {
+        self.source().add_history_entry(line);
+
+        if let Some(history_file) = &self.history_file {
+            let saved = self.source().append_history(history_file);
+            if let Err(err) = saved {
+                self.print_error(
+                    &anyhow::Error::from(err).context("Failed to save history"),
+                );
+            }
+        }
+    }
+
+    fn stdout(&self) -> Output {
+        // Output to stdout is recorded with the printed lines
+        if self.print_output || self.recording {
+            Output::printer(self.printer.clone())
+        } else {
+            Output::stdout()
+        }
+    }
+
+    fn open_output(
+        &self,
+        redirect: Option<Redirect>,
+    ) -> anyhow::Result<Output> {
+        let target = match redirect {
+            None => return Ok(self.stdout()),
+            Some(Redirect::Pipe(command)) => {
+                if command.is_empty() {
+                    anyhow::bail!("Missing command after '|'");
+                }
+
+                return Output::pipe(command);
+            }
+            Some(Redirect::Truncate(target) | Redirect::Append(target)) => {
+                target
+            }
+        };
+
+        let mut file_names =
+            split_string_expand(target, &self.variables, self.tokenizer);
+        let file_name = match (file_names.next(), file_names.next()) {
+            (Some(file_name), None) => PathBuf::from(file_name?),
+            _ => anyhow::bail!("Expected a single file name after '>'"),
+        };
+
+        Output::file(&file_name, matches!(redirect, Some(Redirect::Append(_))))
+    }
+
+    fn builtin_set(
+        &mut self,
+        args: &[String],
+        output: &mut Output,
+    ) -> anyhow::Result<()> {
+        match args {
+            [] => {
+                for (name, value) in self.variables.iter() {
+                    writeln!(output, "{}={}", name, value)?;
+                }
+            }
+            [name, value] => {
+                if !variables::is_valid_name(name) {
+                    anyhow::bail!("Invalid variable name '{}'", name);
+                }
+
+                self.variables.set(name, value);
+            }
+            _ => anyhow::bail!("Usage: set <name> <value>"),
+        }
+
+        Ok(())
+    }
+
+    /// Reads the next lines from the file at `path`, until it ends
+    fn builtin_source(&mut self, path: &Path) -> anyhow::Result<()> {
+        let script = Script::open(path)?;
+
+        let sourcing = self
+            .scripts
+            .iter()
+            .position(|sourcing| sourcing.path() == script.path());
+
+        if let Some(idx) = sourcing {
+            let cycle: Vec<_> = self.scripts[idx..]
+                .iter()
+                .chain([&script])
+                .map(Script::name)
+                .collect();
+
+            anyhow::bail!("Include cycle: {}", cycle.join(" -> "));
+        }
+
+        self.scripts.push(script);
+
+        Ok(())
+    }
+
+    fn builtin_alias(
+        &mut self,
+        args: &str,
+        output: &mut Output,
+    ) -> anyhow::Result<()> {
+        if args.is_empty() {
+            for (name, expansion) in self.aliases.iter() {
+                writeln!(output, "{} = {}", name, expansion)?;
+            }
+
+            return Ok(());
+        }
+
+        if let Some((name, expansion)) = aliases::parse_definition(args) {
+            self.aliases.set(name, expansion);
+            return Ok(());
+        }
+
+        match self.aliases.get(args) {
+            Some(expansion) => writeln!(output, "{} = {}", args, expansion)?,
+            None => anyhow::bail!("Usage: alias <name> = <command>"),
+        }
+
+        Ok(())
+    }
+
+    fn builtin_unalias(&mut self, name: &str) -> anyhow::Result<()> {
+        if name.is_empty() {
+            anyhow::bail!("Usage: unalias <name>");
+        }
+
+        self.aliases
+            .remove(name)
+            .ok_or_else(|| anyhow::anyhow!("Unknown alias '{}'", name))?;
+
+        Ok(())
+    }
+
+    /// Updates what the line source knows about the commands, before every
+    /// line read from it
+    fn update_context<R>(&mut self, redirects: Redirects)
+    where
+        R: ReplCmd,
+    {
+        let commands = BUILTINS
+            .iter()
+            .filter(|name| !is_defined::<R>(name))
+            .chain(R::command_names())
+            .map(|name| name.to_string())
+            .chain(self.aliases.iter().map(|(name, _)| name.to_string()))
+            .collect();
+
+        let validator = self.validate_input.then(|| {
+            let aliases = self.aliases.clone();
+            let variables = self.variables.clone();
+            let tokenizer = self.tokenizer;
+
+            Box::new(move |line: &str| {
+                validate_line::<R>(
+                    line, &aliases, &variables, tokenizer, redirects,
+                )
+            }) as _
+        });
+
+        self.source().set_context(LineContext {
+            commands,
+            infos: R::commands(),
+            tokenizer: self.tokenizer,
+            theme: self.theme.clone(),
+            hints: self.hints,
+            validator,
+        });
+    }
 }
 
 pub trait ReplCmd {
     fn help() -> &'static str;
-    fn parse<'a, I>(parts: I) -> anyhow::Result<Self>
+
+    /// Names and aliases of all the commands, used for completion
+    fn command_names() -> &'static [&'static str] {
+        &[]
+    }
+
+    /// Names and arguments of all the commands, used for hints
+    fn commands() -> &'static [CommandInfo] {
+        &[]
+    }
+
+    /// The command that stands for the end of input, marked with
+    /// `#[replman(on_eof)]`
+    ///
+    /// If there is one, it's returned when the user presses Ctrl-D or types
+    /// `quit`, instead of [`ReadOutcome::Eof`].
+    fn on_eof() -> Option<Self>
+    where
+        Self: Sized,
+    {
+        None
+    }
+
+    /// Parses a command from the tokens of `line`
+    ///
+    /// `line` is the original line, for commands that take the untouched
+    /// remainder of it (see [`line_remainder`]).
+    fn parse<I>(line: &str, parts: I) -> anyhow::Result<Self>
     where
         Self: Sized,
-        I: Iterator<Item = anyhow::Result<&'a str>> + 'a;
+        I: Iterator<Item = anyhow::Result<String>>;
 
     fn parse_str(s: &str) -> anyhow::Result<Self>
     where
         Self: Sized,
     {
-        Self::parse(split_string_unescape(s))
+        Self::parse(s, split_string_unescape(s, TokenizerConfig::default()))
+    }
+
+    /// The command formatted with [`Debug`](std::fmt::Debug), for the `cmd`
+    /// records of [`ReplBuilder::record`]
+    ///
+    /// The derive implements this if the enum implements `Debug`. Commands
+    /// without it aren't recorded.
+    fn debug_record(&self) -> Option<String> {
+        None
     }
 }
 
+/// Writes a command back as a line that [`ReplCmd::parse_str`] reads as the
+/// same command
+///
+/// Derived along with [`ReplCmd`]. The command is written with its main name
+/// and the arguments with [`ToArg`], quoted with [`quote_arg`]. Trailing
+/// arguments that are `None` or equal to their `#[replman(default)]` are left
+/// out. Arguments marked `#[replman(serde)]` are written with their
+/// `Serialize` impl instead, which needs the `serde` feature. Defaults of
+/// types that don't implement `PartialEq` are always written.
+///
+/// Some commands can't be written so that they're read back the same: if an
+/// argument's type doesn't implement [`ToArg`], if it contains both kinds of
+/// quotes, or if it's `None` but an argument after it isn't.
+/// [`ToCommandLine::to_command_line`] writes them anyway, e.g. for logging,
+/// [`ToCommandLine::try_to_command_line`] fails instead, e.g. to queue the
+/// command again.
+///
+/// With `#[replman(display)]` on the enum, `Display` is derived too and
+/// writes the same line as `to_command_line`.
+///
+/// ```
+/// # use replman::prelude::*;
+/// #[derive(ReplCmd)]
+/// #[replman(display)]
+/// enum Command {
+///     Greet {
+///         name: String,
+///         #[replman(default = "1")]
+///         times: u32,
+///     },
+/// }
+///
+/// let cmd = Command::Greet { name: "John Doe".to_string(), times: 1 };
+/// assert_eq!(cmd.to_command_line(), "Greet 'John Doe'");
+/// assert_eq!(cmd.to_string(), "Greet 'John Doe'");
+/// ```
+pub trait ToCommandLine {
+    /// Writes the line and checks that it's read back as the same command
+    fn write_command_line(&self) -> WrittenLine;
+
+    /// The line, written as closely as possible if it isn't read back as the
+    /// same command
+    ///
+    /// Arguments that can't be quoted are put in single quotes anyway, a
+    /// `None` before other arguments is written as `''` and arguments that
+    /// can't be written at all as `?`.
+    fn to_command_line(&self) -> String {
+        self.write_command_line().line
+    }
+
+    /// The line, or why it wouldn't be read back as the same command
+    fn try_to_command_line(&self) -> anyhow::Result<String> {
+        let written = self.write_command_line();
+        match written.error {
+            Some(err) => Err(err),
+            None => Ok(written.line),
+        }
+    }
+}
+
+/// A command written back as a line, see [`ToCommandLine`]
+pub struct WrittenLine {
+    pub line: String,
+    /// Why the line isn't read back as the same command, if it isn't
+    pub error: Option<anyhow::Error>,
+}
+
+/// The error for commands redirected while reading without an [`Output`]
+const REDIRECT_UNSUPPORTED: &str =
+    "The output of commands can't be redirected in this repl";
+
+/// Checks that `line` can be parsed, without running builtins
+fn validate_line<R>(
+    line: &str,
+    aliases: &Aliases,
+    variables: &Variables,
+    tokenizer: TokenizerConfig,
+    redirects: Redirects,
+) -> anyhow::Result<()>
+where
+    R: ReplCmd,
+{
+    let line = line.trim();
+
+    // History is only recalled once the line is submitted
+    if line.is_empty() || line.starts_with('!') {
+        return Ok(());
+    }
+
+    let line = tokenizer.normalize(line);
+    let line = aliases.expand(&line);
+    let (line, redirect) = if redirects != Redirects::Disabled {
+        split_command_redirect::<R>(&line)
+    } else {
+        (line.as_str(), None)
+    };
+
+    let (cmd, _) = aliases::split_cmd_word(line);
+    let is_builtin = BUILTINS.contains(&cmd) && !is_defined::<R>(cmd);
+
+    if !is_builtin {
+        R::parse(line, split_string_expand(line, variables, tokenizer))?;
+        if redirects == Redirects::BuiltinsOnly && redirect.is_some() {
+            anyhow::bail!(REDIRECT_UNSUPPORTED);
+        }
+    }
+
+    Ok(())
+}
+
+/// Splits off the redirect of `line`, unless it's a raw command, which takes
+/// `|` and `>` as part of its argument
+fn split_command_redirect<R>(line: &str) -> (&str, Option<Redirect<'_>>)
+where
+    R: ReplCmd,
+{
+    let (cmd, _) = aliases::split_cmd_word(line);
+    let is_builtin = BUILTINS.contains(&cmd) && !is_defined::<R>(cmd);
+
+    match command_info::find_command(R::commands(), cmd) {
+        Some(info) if info.is_raw() && !is_builtin => (line, None),
+        _ => split_redirect(line),
+    }
+}
+
+/// Whether `R` parses `cmd` as one of its commands, by name, alias or
+/// `starts_with` prefix
+fn is_defined<R>(cmd: &str) -> bool
+where
+    R: ReplCmd,
+{
+    R::command_names().contains(&cmd)
+        || command_info::find_command(R::commands(), cmd).is_some()
+}
+
+/// The part of `line` after the command word, with its original quoting and
+/// whitespace
+pub fn line_remainder(line: &str) -> &str {
+    aliases::split_cmd_word(line).1.trim_start()
+}
+
 pub trait ReplCmdParse {
     fn parse(item: Option<&str>) -> anyhow::Result<Self>
     where
@@ -128,6 +1329,84 @@ impl_with_from_str!(std::num::NonZeroUsize);
 impl_with_from_str!(std::path::PathBuf);
 impl_with_from_str!(String);
 
+/// Writes an argument back as text, for [`ToCommandLine`]
+pub trait ToArg {
+    /// The unquoted text of the argument, `None` if it's left out
+    fn to_arg(&self) -> Option<String>;
+}
+
+macro_rules! impl_to_arg_with_display {
+    ($($t:ty),*) => {
+        $(
+            impl ToArg for $t {
+                fn to_arg(&self) -> Option<String> {
+                    Some(self.to_string())
+                }
+            }
+        )*
+    };
+}
+
+impl_to_arg_with_display!(
+    std::net::IpAddr,
+    std::net::SocketAddr,
+    bool,
+    char,
+    f32,
+    f64,
+    i8,
+    i16,
+    i32,
+    i64,
+    i128,
+    isize,
+    u8,
+    u16,
+    u32,
+    u64,
+    u128,
+    usize,
+    std::net::Ipv4Addr,
+    std::net::Ipv6Addr,
+    std::net::SocketAddrV4,
+    std::net::SocketAddrV6,
+    std::num::NonZeroI8,
+    std::num::NonZeroI16,
+    std::num::NonZeroI32,
+    std::num::NonZeroI64,
+    std::num::NonZeroI128,
+    std::num::NonZeroIsize,
+    std::num::NonZeroU8,
+    std::num::NonZeroU16,
+    std::num::NonZeroU32,
+    std::num::NonZeroU64,
+    std::num::NonZeroU128,
+    std::num::NonZeroUsize,
+    String
+);
+
+/// Non UTF-8 paths are written lossily
+impl ToArg for std::path::PathBuf {
+    fn to_arg(&self) -> Option<String> {
+        Some(self.to_string_lossy().into_owned())
+    }
+}
+
+impl ToArg for std::ffi::OsString {
+    fn to_arg(&self) -> Option<String> {
+        Some(self.to_string_lossy().into_owned())
+    }
+}
+
+impl<T> ToArg for Option<T>
+where
+    T: ToArg,
+{
+    fn to_arg(&self) -> Option<String> {
+        self.as_ref().and_then(ToArg::to_arg)
+    }
+}
+
 impl<T> ReplCmdParse for Option<T>
 where
     T: FromStr,
@@ -148,133 +1427,99 @@ where
     }
 }
 
-pub fn read_command<R>() -> anyhow::Result<R>
+thread_local! {
+    static THREAD_REPL: RefCell<Option<Repl>> = const { RefCell::new(None) };
+}
+
+/// Reads a command with the [`Repl`] of the current thread, see
+/// [`Repl::read_command`]
+///
+/// The repl is created on the first call and kept, so that the history,
+/// aliases and variables carry over to the next calls. Use [`with_repl`] to
+/// configure it.
+pub fn read_command<R>() -> anyhow::Result<ReadOutcome<R>>
 where
     R: ReplCmd,
 {
-    let mut rl = Editor::<()>::new();
-
-    loop {
-        let line = rl.readline("> ")?;
-
-        if line.trim().is_empty() {
-            continue;
-        }
-
-        match R::parse(split_string_unescape(line.trim())) {
-            Ok(cmd) => return Ok(cmd),
-            Err(err) => eprintln!("Failed to parse command: {}", err),
-        }
-    }
+    with_repl(|repl| repl.read_command())
 }
 
-fn split_string_unescape(
-    mut s: &str,
-) -> impl Iterator<Item = anyhow::Result<&str>> {
-    std::iter::from_fn(move || {
-        if s.is_empty() {
-            return None;
-        }
-
-        let next_unescaped_space = find_next_unescaped_space(s);
-
-        let ret = match next_unescaped_space {
-            Ok(x) => match x {
-                Some(x) => {
-                    let ret = &s[..x];
-                    s = &s[x + 1..];
-
-                    ret
-                }
-                None => {
-                    let temp = s;
-                    s = "";
-
-                    temp
-                }
-            },
-            Err(err) => return Some(Err(err)),
-        };
-
-        Some(Ok(unescape(ret)))
+/// Runs `f` with the [`Repl`] used by [`read_command`] on the current thread
+///
+/// ```no_run
+/// # use replman::prelude::*;
+/// replman::with_repl(|repl| -> anyhow::Result<()> {
+///     *repl = Repl::builder().prompt("db> ").build()?;
+///     Ok(())
+/// })?;
+/// # Ok::<(), anyhow::Error>(())
+/// ```
+///
+/// # Panics
+///
+/// If called from within `f`, or if the repl has to be created and
+/// [`Repl::new`] panics.
+pub fn with_repl<T>(f: impl FnOnce(&mut Repl) -> T) -> T {
+    THREAD_REPL.with(|repl| {
+        let mut repl = repl.borrow_mut();
+        f(repl.get_or_insert_with(Repl::new))
     })
 }
 
-fn unescape(s: &str) -> &str {
-    if (s.starts_with('"') && s.ends_with('"'))
-        || (s.starts_with('\'') && s.ends_with('\''))
-    {
-        &s[1..s.len() - 1]
-    } else {
-        s
-    }
-}
+#[cfg(test)]
+mod tests {
+    use test_case::test_case;
 
-fn find_next_unescaped_space(s: &str) -> anyhow::Result<Option<usize>> {
-    let mut is_in_double_qoutes = false;
-    let mut is_in_single_quotes = false;
+    use super::*;
 
-    let mut previous_was_quoted = false;
-    for (idx, c) in s.char_indices() {
-        if previous_was_quoted && c != ' ' {
-            return Err(anyhow::anyhow!(
-                "Invalid command fragment, expected a space or end of string, found '{}'",
-                c
-            ));
-        }
+    struct Number(#[allow(dead_code)] u32);
 
-        match c {
-            '"' if !is_in_double_qoutes && !is_in_single_quotes => {
-                is_in_double_qoutes = true
-            }
-            '"' if is_in_double_qoutes => {
-                is_in_double_qoutes = false;
-                previous_was_quoted = true;
-            }
-            '\'' if !is_in_double_qoutes && !is_in_single_quotes => {
-                is_in_single_quotes = true
-            }
-            '\'' if is_in_single_quotes => {
-                is_in_single_quotes = false;
-                previous_was_quoted = true;
-            }
-            _ => previous_was_quoted = false,
+    impl ReplCmd for Number {
+        fn help() -> &'static str {
+            "number <0>\n"
         }
 
-        if is_in_double_qoutes || is_in_single_quotes {
-            continue;
+        fn command_names() -> &'static [&'static str] {
+            &["number"]
         }
 
-        if c == ' ' {
-            return Ok(Some(idx));
+        fn parse<I>(_line: &str, mut parts: I) -> anyhow::Result<Self>
+        where
+            I: Iterator<Item = anyhow::Result<String>>,
+        {
+            match parts.next().transpose()?.as_deref() {
+                Some("number") => Ok(Self(ReplCmdParse::parse(
+                    parts.next().transpose()?.as_deref(),
+                )?)),
+                _ => anyhow::bail!("unrecognized command"),
+            }
         }
     }
 
-    Ok(None)
-}
-
-#[cfg(test)]
-mod tests {
-    use test_case::test_case;
+    #[test_case("number 1", Redirects::Enabled, true ; "Valid")]
+    #[test_case("number x", Redirects::Enabled, false ; "Invalid")]
+    #[test_case("numbr 1", Redirects::Enabled, false ; "Unknown")]
+    #[test_case("n $x", Redirects::Enabled, true ; "Alias and variable")]
+    #[test_case("number $undefined_variable", Redirects::Enabled, false ; "Undefined variable")]
+    #[test_case("set y 'z", Redirects::Enabled, true ; "Builtin")]
+    #[test_case("number 1 | wc", Redirects::Enabled, true ; "Redirect")]
+    #[test_case("number 1 | wc", Redirects::BuiltinsOnly, false ; "Unsupported redirect")]
+    #[test_case("set | wc", Redirects::BuiltinsOnly, true ; "Builtin redirect")]
+    #[test_case("  ", Redirects::Enabled, true ; "Blank")]
+    fn validate(line: &str, redirects: Redirects, valid: bool) {
+        let mut aliases = Aliases::default();
+        aliases.set("n", "number");
+        let mut variables = Variables::default();
+        variables.set("x", "2");
 
-    use super::*;
+        let result = validate_line::<Number>(
+            line,
+            &aliases,
+            &variables,
+            TokenizerConfig::default(),
+            redirects,
+        );
 
-    #[test_case("Hello", vec!["Hello"] ; "Single item")]
-    #[test_case("Hello World!", vec!["Hello", "World!"] ; "Two items")]
-    #[test_case("", vec![] ; "Empty")]
-    fn basic(s: &str, exp: Vec<&str>) {
-        let actual: Vec<_> =
-            split_string_unescape(s).map(Result::unwrap).collect();
-        assert_eq!(actual, exp);
-    }
-
-    #[test_case(r#""Hello, World!""#, vec!["Hello, World!"] ; "Single - double quotes")]
-    #[test_case(r#"'Hello, World!'"#, vec!["Hello, World!"] ; "Single - single quotes")]
-    #[test_case(r#"'Hello, World!' "What is going on?""#, vec!["Hello, World!", "What is going on?"] ; "Two items - mixed")]
-    #[test_case(r#""" "" """#, vec!["", "", ""] ; "Sequence of double quotes")]
-    fn escaped(s: &str, exp: Vec<&str>) {
-        let actual: Vec<_> =
-            split_string_unescape(s).map(Result::unwrap).collect();
-        assert_eq!(actual, exp);
+        assert_eq!(result.is_ok(), valid);
     }
 }
@@ -0,0 +1,268 @@
+use std::collections::VecDeque;
+use std::io::{self, BufRead, BufReader, IsTerminal, Stdin, Write};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+use crate::command_info::CommandInfo;
+use crate::highlight::Theme;
+use crate::prompt::Prompt;
+use crate::tokenizer::TokenizerConfig;
+
+/// Checks whether a line can be parsed, see [`LineContext::validator`]
+pub type LineValidator = Box<dyn Fn(&str) -> anyhow::Result<()> + Send>;
+
+/// Prints a message above the line being edited, see
+/// [`LineSource::external_printer`]
+pub type PrintHook = Box<dyn FnMut(String) -> io::Result<()> + Send>;
+
+/// The result of reading from a [`LineSource`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ReadLine {
+    Line(String),
+    /// The user pressed Ctrl-C
+    Interrupted,
+    /// The user pressed Ctrl-D or the input ended
+    Eof,
+}
+
+/// What the repl knows about the commands, handed to the [`LineSource`]
+/// before every line for completion, hints, highlighting and validation
+#[non_exhaustive]
+pub struct LineContext {
+    /// Command words of the builtins, commands and aliases
+    pub commands: Vec<String>,
+    /// Arguments of the commands
+    pub infos: &'static [CommandInfo],
+    pub tokenizer: TokenizerConfig,
+    /// Colors of the input line, `None` if highlighting is disabled
+    pub theme: Option<Theme>,
+    pub hints: bool,
+    /// Checks the line when it's submitted, `None` if any line is accepted
+    pub validator: Option<LineValidator>,
+}
+
+/// Where a [`crate::Repl`] reads the lines typed by the user from
+///
+/// rustyline is used by default, with the `rustyline` feature. Other line
+/// editors can be plugged in with [`crate::ReplBuilder::line_source`].
+pub trait LineSource: Send {
+    /// Shows `prompt` and reads the next line, with `initial` already in the
+    /// edit buffer if the source supports editing
+    fn read_line(
+        &mut self,
+        prompt: &Prompt,
+        initial: Option<&str>,
+    ) -> io::Result<ReadLine>;
+
+    /// Records a line that was run
+    fn add_history_entry(&mut self, line: &str);
+
+    /// The recorded lines, oldest first, for `history` and `!n`
+    fn history(&self) -> Vec<String>;
+
+    /// Updates what the source knows about the commands, sources without
+    /// completion can ignore it
+    fn set_context(&mut self, _context: LineContext) {}
+
+    fn load_history(&mut self, _path: &Path) -> io::Result<()> {
+        Ok(())
+    }
+
+    /// Saves the history to `path`, called after every new entry
+    fn append_history(&mut self, _path: &Path) -> io::Result<()> {
+        Ok(())
+    }
+
+    /// A way to print while a line is being read, `None` if output can be
+    /// written to stdout directly
+    fn external_printer(&mut self) -> Option<PrintHook> {
+        None
+    }
+}
+
+/// Reads lines from a [`BufRead`] without any editing, e.g. when rustyline
+/// isn't available
+///
+/// The prompt is printed to stdout, without styling if stdout isn't a
+/// terminal.
+pub struct BufReadSource<B> {
+    reader: B,
+    history: Vec<String>,
+}
+
+impl<B> BufReadSource<B>
+where
+    B: BufRead + Send,
+{
+    pub fn new(reader: B) -> Self {
+        Self {
+            reader,
+            history: vec![],
+        }
+    }
+}
+
+impl BufReadSource<BufReader<Stdin>> {
+    pub fn stdin() -> Self {
+        Self::new(BufReader::new(io::stdin()))
+    }
+}
+
+impl<B> LineSource for BufReadSource<B>
+where
+    B: BufRead + Send,
+{
+    fn read_line(
+        &mut self,
+        prompt: &Prompt,
+        _initial: Option<&str>,
+    ) -> io::Result<ReadLine> {
+        let mut stdout = io::stdout().lock();
+        let rendered = prompt.render(None);
+        if stdout.is_terminal() {
+            write!(stdout, "{}", rendered.styled)?;
+        } else {
+            write!(stdout, "{}", rendered.plain)?;
+        }
+        stdout.flush()?;
+
+        let mut line = String::new();
+        if self.reader.read_line(&mut line)? == 0 {
+            return Ok(ReadLine::Eof);
+        }
+
+        let len = line.trim_end_matches(['\n', '\r']).len();
+        line.truncate(len);
+
+        Ok(ReadLine::Line(line))
+    }
+
+    fn add_history_entry(&mut self, line: &str) {
+        self.history.push(line.to_string());
+    }
+
+    fn history(&self) -> Vec<String> {
+        self.history.clone()
+    }
+}
+
+/// Lines fed from memory, for tests
+///
+/// Clones share the same lines and history, so more lines can be pushed
+/// after the source was handed to the repl. Nothing is printed.
+///
+/// ```
+/// # use replman::prelude::*;
+/// # use replman::{ReadLine, ScriptedSource};
+/// let source = ScriptedSource::new(["help"]);
+/// let repl = Repl::builder().line_source(source.clone()).build()?;
+///
+/// source.push(ReadLine::Interrupted);
+/// source.push_line("quit");
+/// # Ok::<(), anyhow::Error>(())
+/// ```
+#[derive(Clone, Default)]
+pub struct ScriptedSource {
+    inner: Arc<Mutex<Scripted>>,
+}
+
+#[derive(Default)]
+struct Scripted {
+    lines: VecDeque<ReadLine>,
+    history: Vec<String>,
+}
+
+impl ScriptedSource {
+    pub fn new<I>(lines: I) -> Self
+    where
+        I: IntoIterator,
+        I::Item: Into<String>,
+    {
+        let source = Self::default();
+        for line in lines {
+            source.push_line(line);
+        }
+
+        source
+    }
+
+    pub fn push_line(&self, line: impl Into<String>) {
+        self.push(ReadLine::Line(line.into()));
+    }
+
+    pub fn push(&self, line: ReadLine) {
+        self.inner().lines.push_back(line);
+    }
+
+    /// Number of lines that weren't read yet
+    pub fn remaining(&self) -> usize {
+        self.inner().lines.len()
+    }
+
+    fn inner(&self) -> std::sync::MutexGuard<'_, Scripted> {
+        self.inner.lock().expect("Scripted source lock poisoned")
+    }
+}
+
+impl LineSource for ScriptedSource {
+    /// Returns [`ReadLine::Eof`] once all lines were read
+    fn read_line(
+        &mut self,
+        _prompt: &Prompt,
+        _initial: Option<&str>,
+    ) -> io::Result<ReadLine> {
+        Ok(self.inner().lines.pop_front().unwrap_or(ReadLine::Eof))
+    }
+
+    fn add_history_entry(&mut self, line: &str) {
+        self.inner().history.push(line.to_string());
+    }
+
+    fn history(&self) -> Vec<String> {
+        self.inner().history.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+
+    #[test]
+    fn buf_read_lines() {
+        let mut source = BufReadSource::new(Cursor::new("first\r\nsecond"));
+        let prompt = Prompt::new("");
+
+        let mut lines = vec![];
+        while let ReadLine::Line(line) =
+            source.read_line(&prompt, None).unwrap()
+        {
+            lines.push(line);
+        }
+
+        assert_eq!(lines, vec!["first", "second"]);
+    }
+
+    #[test]
+    fn scripted_clones_share_lines() {
+        let source = ScriptedSource::new(["first"]);
+        let mut reader = source.clone();
+        let prompt = Prompt::new("> ");
+
+        source.push(ReadLine::Interrupted);
+        reader.add_history_entry("first");
+
+        assert_eq!(
+            reader.read_line(&prompt, None).unwrap(),
+            ReadLine::Line("first".to_string())
+        );
+        assert_eq!(
+            reader.read_line(&prompt, None).unwrap(),
+            ReadLine::Interrupted
+        );
+        assert_eq!(reader.read_line(&prompt, None).unwrap(), ReadLine::Eof);
+        assert_eq!(source.history(), vec!["first"]);
+        assert_eq!(source.remaining(), 0);
+    }
+}
@@ -0,0 +1,31 @@
+/// The result of reading from a [`crate::Repl`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ReadOutcome<T> {
+    Command(T),
+    /// The user pressed Ctrl-C, only returned with
+    /// [`crate::InterruptBehavior::Return`]
+    Interrupted,
+    /// The input ended, the user pressed Ctrl-D or typed `quit`
+    ///
+    /// Not returned if the command has a `#[replman(on_eof)]` variant, that
+    /// variant is returned as a command instead.
+    Eof,
+}
+
+impl<T> ReadOutcome<T> {
+    pub fn map<U>(self, f: impl FnOnce(T) -> U) -> ReadOutcome<U> {
+        match self {
+            Self::Command(cmd) => ReadOutcome::Command(f(cmd)),
+            Self::Interrupted => ReadOutcome::Interrupted,
+            Self::Eof => ReadOutcome::Eof,
+        }
+    }
+
+    /// The command, if one was read
+    pub fn command(self) -> Option<T> {
+        match self {
+            Self::Command(cmd) => Some(cmd),
+            Self::Interrupted | Self::Eof => None,
+        }
+    }
+}
@@ -0,0 +1,216 @@
+use std::fs::{File, OpenOptions};
+use std::io::{self, Write};
+use std::path::Path;
+use std::process::{Child, ChildStdin, Command, Stdio};
+
+use crate::ReplPrinter;
+
+/// Where the output of a command should be written
+///
+/// Depending on the line the user typed, this is either stdout, a file
+/// (`cmd > file`, `cmd >> file`) or the stdin of a shell pipeline
+/// (`cmd | grep foo`).
+///
+/// Call [`Output::finish`] once the command is done writing, to wait for the
+/// pipeline to exit. Dropping the output does the same, ignoring errors.
+///
+/// Once the pipeline stops reading its input, e.g. `cmd | head -1`, further
+/// writes are discarded.
+pub struct Output {
+    inner: OutputInner,
+}
+
+enum OutputInner {
+    Stdout(io::Stdout),
+    /// Stdout of a repl that captures its output, see
+    /// [`crate::testing::ScriptedRepl`]
+    Printer(ReplPrinter),
+    File(File),
+    Pipe {
+        command: String,
+        child: Child,
+        /// `None` once the pipeline closed its input
+        stdin: Option<ChildStdin>,
+    },
+    Finished,
+}
+
+impl Output {
+    pub fn stdout() -> Self {
+        Self {
+            inner: OutputInner::Stdout(io::stdout()),
+        }
+    }
+
+    pub(crate) fn printer(printer: ReplPrinter) -> Self {
+        Self {
+            inner: OutputInner::Printer(printer),
+        }
+    }
+
+    /// Pipes the output into `sh -c <command>`
+    pub(crate) fn pipe(command: &str) -> anyhow::Result<Self> {
+        let mut child = Command::new("sh")
+            .arg("-c")
+            .arg(command)
+            .stdin(Stdio::piped())
+            .spawn()?;
+        let stdin = child.stdin.take();
+
+        Ok(Self {
+            inner: OutputInner::Pipe {
+                command: command.to_string(),
+                child,
+                stdin,
+            },
+        })
+    }
+
+    pub(crate) fn file(path: &Path, append: bool) -> anyhow::Result<Self> {
+        let file = OpenOptions::new()
+            .create(true)
+            .write(true)
+            .append(append)
+            .truncate(!append)
+            .open(path)?;
+
+        Ok(Self {
+            inner: OutputInner::File(file),
+        })
+    }
+
+    /// Flushes the output and waits for the shell pipeline, if any, to exit
+    pub fn finish(mut self) -> anyhow::Result<()> {
+        self.finish_inner()
+    }
+
+    fn finish_inner(&mut self) -> anyhow::Result<()> {
+        match std::mem::replace(&mut self.inner, OutputInner::Finished) {
+            OutputInner::Stdout(mut stdout) => stdout.flush()?,
+            // Prints the last line even if it doesn't end in a newline
+            OutputInner::Printer(mut printer) => printer.flush()?,
+            OutputInner::File(mut file) => file.flush()?,
+            OutputInner::Pipe {
+                command,
+                mut child,
+                stdin,
+            } => {
+                // Closing stdin lets the pipeline know there's no more input
+                drop(stdin);
+                let status = child.wait()?;
+                if !status.success() {
+                    anyhow::bail!("'{}' failed: {}", command, status);
+                }
+            }
+            OutputInner::Finished => (),
+        }
+
+        Ok(())
+    }
+}
+
+impl Write for Output {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match &mut self.inner {
+            OutputInner::Stdout(stdout) => stdout.write(buf),
+            OutputInner::Printer(printer) => printer.write(buf),
+            OutputInner::File(file) => file.write(buf),
+            OutputInner::Pipe { stdin, .. } => {
+                let written = match stdin {
+                    Some(pipe) => pipe.write(buf),
+                    None => return Ok(buf.len()),
+                };
+
+                match written {
+                    Err(err) if err.kind() == io::ErrorKind::BrokenPipe => {
+                        *stdin = None;
+                        Ok(buf.len())
+                    }
+                    written => written,
+                }
+            }
+            OutputInner::Finished => Err(io::ErrorKind::BrokenPipe.into()),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match &mut self.inner {
+            OutputInner::Stdout(stdout) => stdout.flush(),
+            // Complete lines are printed right away
+            OutputInner::Printer(_) => Ok(()),
+            OutputInner::File(file) => file.flush(),
+            OutputInner::Pipe {
+                stdin: Some(stdin), ..
+            } => match stdin.flush() {
+                Err(err) if err.kind() == io::ErrorKind::BrokenPipe => Ok(()),
+                flushed => flushed,
+            },
+            OutputInner::Pipe { stdin: None, .. } | OutputInner::Finished => {
+                Ok(())
+            }
+        }
+    }
+}
+
+impl Drop for Output {
+    fn drop(&mut self) {
+        let _ = self.finish_inner();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pipe_and_redirect() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path();
+        let path = dir.join("out.txt");
+
+        let mut output = Output::file(&path, false).unwrap();
+        writeln!(output, "first").unwrap();
+        output.finish().unwrap();
+
+        let mut output =
+            Output::pipe(&format!("tr a-z A-Z >> {}", path.display())).unwrap();
+        writeln!(output, "second").unwrap();
+        output.finish().unwrap();
+
+        let mut output = Output::file(&path, true).unwrap();
+        writeln!(output, "third").unwrap();
+        output.finish().unwrap();
+
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap(),
+            "first\nSECOND\nthird\n"
+        );
+    }
+
+    #[test]
+    fn closed_pipe() {
+        let tmp = tempfile::tempdir().unwrap();
+        let path = tmp.path().join("out.txt");
+
+        let mut output =
+            Output::pipe(&format!("head -1 > {}", path.display())).unwrap();
+        for idx in 0..100_000 {
+            writeln!(output, "line {}", idx).unwrap();
+        }
+        output.finish().unwrap();
+
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "line 0\n");
+    }
+
+    #[test]
+    fn failed_pipe() {
+        let mut output = Output::pipe("cat > /dev/null; exit 3").unwrap();
+        writeln!(output, "line").unwrap();
+
+        let err = output.finish().unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "'cat > /dev/null; exit 3' failed: exit status: 3"
+        );
+    }
+}
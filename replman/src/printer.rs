@@ -0,0 +1,181 @@
+use std::io::{self, Write};
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use crate::line_source::PrintHook;
+use crate::recording::{RecordKind, Recording};
+
+/// Prints messages without corrupting the prompt and the line being edited
+///
+/// While the user is typing, the prompt line is cleared, the message is
+/// printed and the prompt and edit buffer are redrawn below it. Otherwise
+/// messages are printed as is.
+///
+/// The printer is cheap to clone and can be moved to background threads or
+/// tasks, see [`crate::Repl::printer`].
+///
+/// It also implements [`Write`], buffering output until a full line was
+/// written, so it can be used as the target of a logger:
+///
+/// ```no_run
+/// # use std::io::Write;
+/// # use replman::prelude::*;
+/// let repl = Repl::new();
+/// let mut printer = repl.printer();
+///
+/// std::thread::spawn(move || {
+///     writeln!(printer, "Background job finished").unwrap();
+/// });
+/// ```
+pub struct ReplPrinter {
+    shared: Arc<Mutex<Shared>>,
+    /// Output of [`Write`] not ending in a newline yet, not shared between
+    /// clones
+    buffer: Vec<u8>,
+}
+
+struct Shared {
+    /// `None` if the line source can't print while a line is read, messages
+    /// are written to stdout directly then
+    printer: Option<PrintHook>,
+    /// Printed lines are recorded too, see [`crate::ReplBuilder::record`]
+    recording: Option<Recording>,
+}
+
+impl ReplPrinter {
+    pub(crate) fn new(printer: Option<PrintHook>) -> Self {
+        Self {
+            shared: Arc::new(Mutex::new(Shared {
+                printer,
+                recording: None,
+            })),
+            buffer: Vec::new(),
+        }
+    }
+
+    pub(crate) fn set_recording(&self, recording: Option<Recording>) {
+        self.shared().recording = recording;
+    }
+
+    /// Adds a record to the recording, if there is one
+    pub(crate) fn record(
+        &self,
+        kind: RecordKind,
+        text: &str,
+    ) -> io::Result<()> {
+        match &mut self.shared().recording {
+            Some(recording) => recording.record(kind, text),
+            None => Ok(()),
+        }
+    }
+
+    /// Prints `msg` followed by a newline
+    pub fn println(&self, msg: impl Into<String>) -> anyhow::Result<()> {
+        let mut msg = msg.into();
+        if !msg.ends_with('\n') {
+            msg.push('\n');
+        }
+
+        let mut shared = self.shared();
+        if let Some(recording) = &mut shared.recording {
+            for line in msg.lines() {
+                recording.record(RecordKind::Output, line)?;
+            }
+        }
+
+        match &mut shared.printer {
+            Some(printer) => printer(msg)?,
+            None => {
+                let mut stdout = io::stdout().lock();
+                stdout.write_all(msg.as_bytes())?;
+                stdout.flush()?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn shared(&self) -> MutexGuard<'_, Shared> {
+        self.shared.lock().expect("Printer lock poisoned")
+    }
+
+    fn print_buffered_lines(&mut self) -> io::Result<()> {
+        let end = match self.buffer.iter().rposition(|&b| b == b'\n') {
+            Some(end) => end,
+            None => return Ok(()),
+        };
+
+        let lines: Vec<_> = self.buffer.drain(..=end).collect();
+        self.println(String::from_utf8_lossy(&lines))
+            .map_err(io::Error::other)
+    }
+}
+
+impl Clone for ReplPrinter {
+    fn clone(&self) -> Self {
+        Self {
+            shared: self.shared.clone(),
+            buffer: Vec::new(),
+        }
+    }
+}
+
+impl Write for ReplPrinter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.buffer.extend_from_slice(buf);
+        self.print_buffered_lines()?;
+
+        Ok(buf.len())
+    }
+
+    /// Prints the buffered output, even if it isn't a complete line
+    fn flush(&mut self) -> io::Result<()> {
+        if !self.buffer.is_empty() {
+            self.buffer.push(b'\n');
+        }
+
+        self.print_buffered_lines()
+    }
+}
+
+impl Drop for ReplPrinter {
+    fn drop(&mut self) {
+        let _ = self.flush();
+    }
+}
+
+#[cfg(feature = "tracing")]
+impl<'a> tracing_subscriber::fmt::MakeWriter<'a> for ReplPrinter {
+    type Writer = ReplPrinter;
+
+    fn make_writer(&'a self) -> Self::Writer {
+        self.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn write_buffers_lines() {
+        let printed = Arc::new(Mutex::new(Vec::new()));
+        let recorded = printed.clone();
+        let mut printer = ReplPrinter::new(Some(Box::new(move |msg| {
+            recorded.lock().unwrap().push(msg);
+            Ok(())
+        })));
+
+        write!(printer, "first").unwrap();
+        assert!(printed.lock().unwrap().is_empty());
+
+        writeln!(printer, " line\nsecond line").unwrap();
+        write!(printer.clone(), "third").unwrap();
+        write!(printer, "fourth").unwrap();
+        drop(printer);
+
+        assert_eq!(
+            *printed.lock().unwrap(),
+            vec!["first line\nsecond line\n", "third\n", "fourth\n"]
+        );
+    }
+}
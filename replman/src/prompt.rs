@@ -0,0 +1,158 @@
+use std::borrow::Cow;
+
+use unicode_width::UnicodeWidthStr;
+
+/// The prompt displayed before the input line
+///
+/// The text may contain ANSI escape sequences for styling, they are not
+/// counted towards the width of the prompt. The optional right side text is
+/// displayed at the right edge of the terminal, e.g. for status information.
+///
+/// ```
+/// # use replman::Prompt;
+/// let prompt = Prompt::new("\x1b[1;32m[prod-db:3]\x1b[0m > ").with_right("12ms");
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Prompt {
+    text: String,
+    right: Option<String>,
+}
+
+impl Prompt {
+    pub fn new(text: impl Into<String>) -> Self {
+        Self {
+            text: text.into(),
+            right: None,
+        }
+    }
+
+    pub fn with_right(mut self, right: impl Into<String>) -> Self {
+        self.right = Some(right.into());
+        self
+    }
+
+    /// Renders the prompt for a terminal `columns` wide, if known
+    pub(crate) fn render(&self, columns: Option<usize>) -> RenderedPrompt {
+        let plain = strip_ansi(&self.text).into_owned();
+        let mut styled = String::new();
+
+        if let (Some(right), Some(columns)) = (&self.right, columns) {
+            let right_width = display_width(right);
+
+            // Leave at least a bit of room for the input
+            if display_width(&self.text) + right_width + 8 < columns {
+                let column = columns - right_width + 1;
+
+                // Save the cursor, draw the right side and restore the cursor
+                styled
+                    .push_str(&format!("\x1b7\x1b[{}G{}\x1b8", column, right));
+            }
+        }
+
+        styled.push_str(&self.text);
+
+        RenderedPrompt { plain, styled }
+    }
+}
+
+impl From<&str> for Prompt {
+    fn from(text: &str) -> Self {
+        Self::new(text)
+    }
+}
+
+impl From<String> for Prompt {
+    fn from(text: String) -> Self {
+        Self::new(text)
+    }
+}
+
+/// A prompt ready to be handed over to the line editor
+///
+/// The editor gets the plain text, so that its cursor math is right, and
+/// swaps in the styled version when drawing.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct RenderedPrompt {
+    pub plain: String,
+    pub styled: String,
+}
+
+/// Removes ANSI escape sequences from `s`
+pub(crate) fn strip_ansi(s: &str) -> Cow<'_, str> {
+    if !s.contains('\x1b') {
+        return Cow::Borrowed(s);
+    }
+
+    let mut ret = String::with_capacity(s.len());
+    let mut chars = s.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if c != '\x1b' {
+            ret.push(c);
+            continue;
+        }
+
+        match chars.next() {
+            // CSI sequences end with a byte in the range `@` to `~`
+            Some('[') => {
+                for c in chars.by_ref() {
+                    if ('@'..='~').contains(&c) {
+                        break;
+                    }
+                }
+            }
+            // OSC sequences end with BEL or ST
+            Some(']') => {
+                while let Some(c) = chars.next() {
+                    if c == '\x07'
+                        || (c == '\x1b' && chars.peek() == Some(&'\\'))
+                    {
+                        if c == '\x1b' {
+                            chars.next();
+                        }
+                        break;
+                    }
+                }
+            }
+            // Two character sequences, e.g. `ESC 7`
+            _ => (),
+        }
+    }
+
+    Cow::Owned(ret)
+}
+
+pub(crate) fn display_width(s: &str) -> usize {
+    strip_ansi(s).width()
+}
+
+#[cfg(test)]
+mod tests {
+    use test_case::test_case;
+
+    use super::*;
+
+    #[test_case("> ", "> " ; "Plain")]
+    #[test_case("\x1b[1;32m[db]\x1b[0m > ", "[db] > " ; "Colored")]
+    #[test_case("\x1b]0;title\x07> ", "> " ; "Window title")]
+    fn strips_ansi(s: &str, exp: &str) {
+        assert_eq!(strip_ansi(s), exp);
+    }
+
+    #[test]
+    fn width_ignores_styling() {
+        assert_eq!(display_width("\x1b[31mżółw\x1b[0m > "), 7);
+    }
+
+    #[test]
+    fn renders_right_side() {
+        let prompt = Prompt::new("\x1b[32m>\x1b[0m ").with_right("ok");
+
+        let rendered = prompt.render(Some(40));
+        assert_eq!(rendered.plain, "> ");
+        assert_eq!(rendered.styled, "\x1b7\x1b[39Gok\x1b8\x1b[32m>\x1b[0m ");
+
+        let rendered = prompt.render(None);
+        assert_eq!(rendered.styled, "\x1b[32m>\x1b[0m ");
+    }
+}
@@ -0,0 +1,154 @@
+use std::fs::File;
+use std::io::{self, LineWriter, Write};
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::Context;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum RecordKind {
+    Input,
+    Command,
+    Output,
+    Error,
+}
+
+impl RecordKind {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Input => "in",
+            Self::Command => "cmd",
+            Self::Output => "out",
+            Self::Error => "err",
+        }
+    }
+}
+
+/// The file a session is recorded to
+pub(crate) struct Recording {
+    file: LineWriter<File>,
+}
+
+impl Recording {
+    pub fn create(path: &Path) -> anyhow::Result<Self> {
+        let file = File::create(path).with_context(|| {
+            format!("Failed to create recording {}", path.display())
+        })?;
+
+        Ok(Self {
+            file: LineWriter::new(file),
+        })
+    }
+
+    pub fn record(&mut self, kind: RecordKind, text: &str) -> io::Result<()> {
+        let time = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default();
+
+        writeln!(
+            self.file,
+            "{}.{:03} {} {}",
+            time.as_secs(),
+            time.subsec_millis(),
+            kind.as_str(),
+            escape(text)
+        )
+    }
+}
+
+/// The `in` records of the recording at `path`, in order
+pub(crate) fn read_inputs(path: &Path) -> anyhow::Result<Vec<String>> {
+    let recording = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+
+    let mut inputs = vec![];
+    for (idx, line) in recording.lines().enumerate() {
+        let invalid = || {
+            anyhow::anyhow!("{}:{}: Invalid record", path.display(), idx + 1)
+        };
+
+        let mut fields = line.splitn(3, ' ');
+        let (time, kind, text) =
+            match (fields.next(), fields.next(), fields.next()) {
+                (Some(time), Some(kind), Some(text)) => (time, kind, text),
+                // Empty text has no trailing space to split on
+                (Some(time), Some(kind), None) => (time, kind, ""),
+                _ => return Err(invalid()),
+            };
+
+        if time.parse::<f64>().is_err() {
+            return Err(invalid());
+        }
+
+        if kind == RecordKind::Input.as_str() {
+            inputs.push(unescape(text).ok_or_else(invalid)?);
+        }
+    }
+
+    Ok(inputs)
+}
+
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace('\n', "\\n")
+        .replace('\r', "\\r")
+}
+
+fn unescape(text: &str) -> Option<String> {
+    let mut ret = String::with_capacity(text.len());
+    let mut chars = text.chars();
+
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            ret.push(c);
+            continue;
+        }
+
+        ret.push(match chars.next()? {
+            '\\' => '\\',
+            'n' => '\n',
+            'r' => '\r',
+            _ => return None,
+        });
+    }
+
+    Some(ret)
+}
+
+#[cfg(test)]
+mod tests {
+    use test_case::test_case;
+
+    use super::*;
+
+    #[test_case("plain" ; "Plain")]
+    #[test_case("a\\nb\\" ; "Backslashes")]
+    #[test_case("first\r\nsecond\n" ; "Newlines")]
+    #[test_case("" ; "Empty")]
+    fn escapes(text: &str) {
+        let escaped = escape(text);
+
+        assert!(!escaped.contains(['\n', '\r']));
+        assert_eq!(unescape(&escaped).as_deref(), Some(text));
+    }
+
+    #[test]
+    fn reads_inputs() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path();
+        let path = dir.join("session.rec");
+
+        let mut recording = Recording::create(&path).unwrap();
+        recording.record(RecordKind::Input, "greet a\\b").unwrap();
+        recording.record(RecordKind::Command, "Greet").unwrap();
+        recording.record(RecordKind::Output, "hi").unwrap();
+        recording.record(RecordKind::Input, "").unwrap();
+        drop(recording);
+
+        assert_eq!(read_inputs(&path).unwrap(), vec!["greet a\\b", ""]);
+
+        std::fs::write(&path, "1.000 in ok\nnot a record\n").unwrap();
+        let err = read_inputs(&path).unwrap_err().to_string();
+        assert!(err.ends_with("session.rec:2: Invalid record"), "{}", err);
+    }
+}
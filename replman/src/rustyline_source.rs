@@ -0,0 +1,104 @@
+use std::io;
+use std::path::Path;
+
+use rustyline::error::ReadlineError;
+use rustyline::{Config, Editor, ExternalPrinter};
+
+use crate::helper::ReplHelper;
+use crate::line_source::{LineContext, LineSource, PrintHook, ReadLine};
+use crate::prompt::Prompt;
+
+/// Reads lines with rustyline, with completion, hints, highlighting and
+/// validation
+///
+/// The default [`LineSource`].
+pub struct RustylineSource {
+    editor: Editor<ReplHelper>,
+}
+
+impl RustylineSource {
+    pub fn new() -> anyhow::Result<Self> {
+        Self::with_config(Config::default())
+    }
+
+    pub(crate) fn with_config(config: Config) -> anyhow::Result<Self> {
+        let mut editor = Editor::with_config(config)?;
+        editor.set_helper(Some(ReplHelper::default()));
+
+        Ok(Self { editor })
+    }
+}
+
+impl LineSource for RustylineSource {
+    fn read_line(
+        &mut self,
+        prompt: &Prompt,
+        initial: Option<&str>,
+    ) -> io::Result<ReadLine> {
+        let columns = self.editor.dimensions().map(|(columns, _)| columns);
+        let rendered = prompt.render(columns);
+        let plain = rendered.plain.clone();
+
+        if let Some(helper) = self.editor.helper_mut() {
+            helper.prompt = rendered;
+        }
+
+        let line = match initial {
+            Some(initial) => {
+                self.editor.readline_with_initial(&plain, (initial, ""))
+            }
+            None => self.editor.readline(&plain),
+        };
+
+        match line {
+            Ok(line) => Ok(ReadLine::Line(line)),
+            Err(ReadlineError::Interrupted) => Ok(ReadLine::Interrupted),
+            Err(ReadlineError::Eof) => Ok(ReadLine::Eof),
+            Err(err) => Err(into_io_error(err)),
+        }
+    }
+
+    fn add_history_entry(&mut self, line: &str) {
+        self.editor.add_history_entry(line);
+    }
+
+    fn history(&self) -> Vec<String> {
+        self.editor.history().iter().cloned().collect()
+    }
+
+    fn set_context(&mut self, context: LineContext) {
+        if let Some(helper) = self.editor.helper_mut() {
+            helper.commands = context.commands;
+            helper.infos = context.infos;
+            helper.tokenizer = context.tokenizer;
+            helper.theme = context.theme;
+            helper.hints = context.hints;
+            helper.validator = context.validator;
+        }
+    }
+
+    fn load_history(&mut self, path: &Path) -> io::Result<()> {
+        self.editor.load_history(path).map_err(into_io_error)
+    }
+
+    fn append_history(&mut self, path: &Path) -> io::Result<()> {
+        self.editor.append_history(path).map_err(into_io_error)
+    }
+
+    /// `None` if stdin or stdout isn't a terminal, there's no prompt to
+    /// redraw then
+    fn external_printer(&mut self) -> Option<PrintHook> {
+        let mut printer = self.editor.create_external_printer().ok()?;
+
+        Some(Box::new(move |msg| {
+            printer.print(msg).map_err(into_io_error)
+        }))
+    }
+}
+
+fn into_io_error(err: ReadlineError) -> io::Error {
+    match err {
+        ReadlineError::Io(err) => err,
+        err => io::Error::other(err),
+    }
+}
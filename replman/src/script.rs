@@ -0,0 +1,107 @@
+use std::fs::File;
+use std::io::{self, BufRead, BufReader};
+use std::path::{Path, PathBuf};
+
+use anyhow::Context;
+
+/// Commands read line by line from a file or a pipe instead of the terminal
+///
+/// Lines starting with `#` are comments and skipped.
+pub(crate) struct Script {
+    /// A startup file, see [`crate::ReplBuilder::rc_file`]
+    pub startup: bool,
+    reader: Box<dyn BufRead + Send>,
+    /// File name used in error locations
+    name: String,
+    /// Canonical path of the file, to detect include cycles
+    path: Option<PathBuf>,
+    /// Number of the last line read, starting at 1
+    line_number: usize,
+}
+
+impl Script {
+    pub fn new(reader: impl BufRead + Send + 'static, name: String) -> Self {
+        Self {
+            startup: false,
+            reader: Box::new(reader),
+            name,
+            path: None,
+            line_number: 0,
+        }
+    }
+
+    pub fn open(path: &Path) -> anyhow::Result<Self> {
+        let file = File::open(path)
+            .with_context(|| format!("Failed to open {}", path.display()))?;
+
+        Ok(Self {
+            path: Some(path.canonicalize()?),
+            ..Self::new(BufReader::new(file), path.display().to_string())
+        })
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+
+    pub fn stdin() -> Self {
+        Self::new(BufReader::new(io::stdin()), "<stdin>".to_string())
+    }
+
+    /// Reads the next line that isn't a comment, `None` at the end
+    pub fn read_line(&mut self) -> io::Result<Option<String>> {
+        loop {
+            let mut line = String::new();
+            if self.reader.read_line(&mut line)? == 0 {
+                return Ok(None);
+            }
+
+            self.line_number += 1;
+
+            if line.trim_start().starts_with('#') {
+                continue;
+            }
+
+            let len = line.trim_end_matches(['\n', '\r']).len();
+            line.truncate(len);
+
+            return Ok(Some(line));
+        }
+    }
+
+    /// `name:line` of the last line read
+    pub fn location(&self) -> String {
+        format!("{}:{}", self.name, self.line_number)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+
+    #[test]
+    fn read_lines() {
+        let input = "first\r\n# comment\n\n  # indented comment\nlast";
+        let mut script = Script::new(Cursor::new(input), "test".to_string());
+
+        let mut lines = vec![];
+        while let Some(line) = script.read_line().unwrap() {
+            lines.push((line, script.location()));
+        }
+
+        assert_eq!(
+            lines,
+            vec![
+                ("first".to_string(), "test:1".to_string()),
+                ("".to_string(), "test:3".to_string()),
+                ("last".to_string(), "test:5".to_string()),
+            ]
+        );
+    }
+}
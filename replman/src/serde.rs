@@ -0,0 +1,279 @@
+//! Commands in serde formats, with the `serde` feature
+//!
+//! Commands are stored as their command line, e.g. in a config file:
+//!
+//! ```
+//! # use replman::prelude::*;
+//! #[derive(Debug, PartialEq, ReplCmd)]
+//! enum Command {
+//!     Connect { host: String, port: u16 },
+//! }
+//!
+//! #[derive(serde::Serialize, serde::Deserialize)]
+//! struct Config {
+//!     #[serde(with = "replman::serde")]
+//!     on_start: Command,
+//! }
+//! ```
+
+use ::serde::de::Error as _;
+use ::serde::ser::{self, Error as _, Impossible, Serialize};
+use ::serde::{Deserialize, Deserializer, Serializer};
+
+use crate::{ReplCmd, ToCommandLine};
+
+/// Serializes `cmd` as its command line
+pub fn serialize<R, S>(cmd: &R, serializer: S) -> Result<S::Ok, S::Error>
+where
+    R: ToCommandLine,
+    S: Serializer,
+{
+    let line = cmd
+        .try_to_command_line()
+        .map_err(|err| S::Error::custom(format!("{:#}", err)))?;
+    serializer.serialize_str(&line)
+}
+
+/// Deserializes a command line and parses it into a command
+pub fn deserialize<'de, R, D>(deserializer: D) -> Result<R, D::Error>
+where
+    R: ReplCmd,
+    D: Deserializer<'de>,
+{
+    let line = String::deserialize(deserializer)?;
+    R::parse_str(&line).map_err(|err| D::Error::custom(format!("{:#}", err)))
+}
+
+/// Writes an argument of a command with its `Serialize` impl, `None` for
+/// `None`
+///
+/// Used by the derived [`ToCommandLine`] for `#[replman(serde)]` arguments,
+/// the result isn't quoted yet. Fails if the value isn't a string, number,
+/// bool, char, fieldless enum or an `Option` of them.
+pub fn to_arg<T>(value: &T) -> anyhow::Result<Option<String>>
+where
+    T: Serialize + ?Sized,
+{
+    Ok(value.serialize(ArgSerializer)?)
+}
+
+#[derive(Debug)]
+struct UnsupportedArg(String);
+
+impl std::fmt::Display for UnsupportedArg {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} can't be written as a command argument", self.0)
+    }
+}
+
+impl std::error::Error for UnsupportedArg {}
+
+impl ser::Error for UnsupportedArg {
+    fn custom<T>(msg: T) -> Self
+    where
+        T: std::fmt::Display,
+    {
+        Self(msg.to_string())
+    }
+}
+
+/// Serializes scalars into the text of a single argument
+struct ArgSerializer;
+
+macro_rules! serialize_display {
+    ($($method:ident: $t:ty),*) => {
+        $(
+            fn $method(self, v: $t) -> Result<Self::Ok, Self::Error> {
+                Ok(Some(v.to_string()))
+            }
+        )*
+    };
+}
+
+macro_rules! unsupported {
+    ($name:expr) => {
+        Err(UnsupportedArg($name.to_string()))
+    };
+}
+
+impl Serializer for ArgSerializer {
+    type Ok = Option<String>;
+    type Error = UnsupportedArg;
+
+    type SerializeSeq = Impossible<Self::Ok, Self::Error>;
+    type SerializeTuple = Impossible<Self::Ok, Self::Error>;
+    type SerializeTupleStruct = Impossible<Self::Ok, Self::Error>;
+    type SerializeTupleVariant = Impossible<Self::Ok, Self::Error>;
+    type SerializeMap = Impossible<Self::Ok, Self::Error>;
+    type SerializeStruct = Impossible<Self::Ok, Self::Error>;
+    type SerializeStructVariant = Impossible<Self::Ok, Self::Error>;
+
+    serialize_display!(
+        serialize_bool: bool,
+        serialize_i8: i8,
+        serialize_i16: i16,
+        serialize_i32: i32,
+        serialize_i64: i64,
+        serialize_i128: i128,
+        serialize_u8: u8,
+        serialize_u16: u16,
+        serialize_u32: u32,
+        serialize_u64: u64,
+        serialize_u128: u128,
+        serialize_f32: f32,
+        serialize_f64: f64,
+        serialize_char: char,
+        serialize_str: &str
+    );
+
+    fn serialize_bytes(self, _v: &[u8]) -> Result<Self::Ok, Self::Error> {
+        unsupported!("Bytes")
+    }
+
+    fn serialize_none(self) -> Result<Self::Ok, Self::Error> {
+        Ok(None)
+    }
+
+    fn serialize_some<T>(self, value: &T) -> Result<Self::Ok, Self::Error>
+    where
+        T: Serialize + ?Sized,
+    {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<Self::Ok, Self::Error> {
+        unsupported!("A unit")
+    }
+
+    fn serialize_unit_struct(
+        self,
+        name: &'static str,
+    ) -> Result<Self::Ok, Self::Error> {
+        unsupported!(name)
+    }
+
+    /// Fieldless enums are written as the name of the variant
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+    ) -> Result<Self::Ok, Self::Error> {
+        Ok(Some(variant.to_string()))
+    }
+
+    fn serialize_newtype_struct<T>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<Self::Ok, Self::Error>
+    where
+        T: Serialize + ?Sized,
+    {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T>(
+        self,
+        name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _value: &T,
+    ) -> Result<Self::Ok, Self::Error>
+    where
+        T: Serialize + ?Sized,
+    {
+        unsupported!(name)
+    }
+
+    fn serialize_seq(
+        self,
+        _len: Option<usize>,
+    ) -> Result<Self::SerializeSeq, Self::Error> {
+        unsupported!("A sequence")
+    }
+
+    fn serialize_tuple(
+        self,
+        _len: usize,
+    ) -> Result<Self::SerializeTuple, Self::Error> {
+        unsupported!("A tuple")
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        name: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleStruct, Self::Error> {
+        unsupported!(name)
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleVariant, Self::Error> {
+        unsupported!(name)
+    }
+
+    fn serialize_map(
+        self,
+        _len: Option<usize>,
+    ) -> Result<Self::SerializeMap, Self::Error> {
+        unsupported!("A map")
+    }
+
+    fn serialize_struct(
+        self,
+        name: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStruct, Self::Error> {
+        unsupported!(name)
+    }
+
+    fn serialize_struct_variant(
+        self,
+        name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStructVariant, Self::Error> {
+        unsupported!(name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+
+    #[derive(serde::Serialize)]
+    enum Level {
+        Debug,
+    }
+
+    #[test]
+    fn args() {
+        assert_eq!(to_arg(&1.5f32).unwrap().as_deref(), Some("1.5"));
+        assert_eq!(to_arg("a b").unwrap().as_deref(), Some("a b"));
+        assert_eq!(
+            to_arg(&PathBuf::from("/tmp")).unwrap().as_deref(),
+            Some("/tmp")
+        );
+        assert_eq!(to_arg(&Some('x')).unwrap().as_deref(), Some("x"));
+        assert_eq!(to_arg(&None::<u32>).unwrap(), None);
+        assert_eq!(to_arg(&Level::Debug).unwrap().as_deref(), Some("Debug"));
+    }
+
+    #[test]
+    fn unsupported() {
+        let err = to_arg(&vec![1, 2]).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "A sequence can't be written as a command argument"
+        );
+    }
+}
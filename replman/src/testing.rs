@@ -0,0 +1,325 @@
+//! Running a [`Repl`] in tests, without a terminal
+//!
+//! ```
+//! # use replman::prelude::*;
+//! # use replman::testing::ScriptedRepl;
+//! #[derive(Debug, PartialEq, ReplCmd)]
+//! enum Command {
+//!     Add(i32, i32),
+//! }
+//!
+//! let mut repl = ScriptedRepl::new();
+//! repl.feed("Add 1 2");
+//! repl.feed("Add 1");
+//!
+//! assert_eq!(
+//!     repl.read_command::<Command>()?,
+//!     ReadOutcome::Command(Command::Add(1, 2))
+//! );
+//! assert_eq!(repl.read_command::<Command>()?, ReadOutcome::Eof);
+//! assert_eq!(repl.take_errors().len(), 1);
+//! # Ok::<(), anyhow::Error>(())
+//! ```
+//!
+//! # Transcripts
+//!
+//! Everything that happens is recorded in a transcript: every line read is
+//! written after `> `, followed by the output of the builtins, the commands
+//! that wrote to their [`crate::Output`], messages of the
+//! [`crate::ReplPrinter`] and errors. Ctrl-C is written as `> ^C`.
+//!
+//! ```text
+//! > set greeting hello
+//! > greet $greeting
+//! hello
+//! > gret
+//! Failed to parse command: unrecognized command 'gret'
+//! ```
+//!
+//! [`ScriptedRepl::run_golden`] feeds the input lines of such a transcript
+//! to a handler and checks that the same transcript comes out. Output lines
+//! starting with `> ` can't be told apart from input lines.
+
+use std::io;
+use std::ops::{Deref, DerefMut};
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use crate::line_source::{LineContext, PrintHook};
+use crate::{
+    LineSource, Prompt, ReadLine, Repl, ReplBuilder, ReplCmd, ReplHandler,
+    ScriptedSource,
+};
+
+/// A [`Repl`] that reads fed lines and captures everything it prints
+///
+/// It derefs to the [`Repl`], so it's used with the same read API, e.g.
+/// [`Repl::read_command`], [`Repl::execute`] and [`Repl::run`]. Commands
+/// should write to their [`crate::Output`] or the [`Repl::printer`] for their
+/// output to be captured, stdout isn't.
+pub struct ScriptedRepl {
+    repl: Repl,
+    source: ScriptedSource,
+    captured: Arc<Mutex<Captured>>,
+}
+
+#[derive(Default)]
+struct Captured {
+    transcript: String,
+    output: String,
+    errors: Vec<String>,
+}
+
+impl Captured {
+    /// Ends the last line of the transcript, if output left it unterminated
+    fn start_line(&mut self) {
+        if !self.transcript.is_empty() && !self.transcript.ends_with('\n') {
+            self.transcript.push('\n');
+        }
+    }
+}
+
+impl ScriptedRepl {
+    #[allow(clippy::new_without_default)]
+    pub fn new() -> Self {
+        Self::with_builder(Repl::builder())
+            .expect("Failed to create the scripted repl")
+    }
+
+    /// Builds the repl with the configuration of `builder`
+    ///
+    /// Its line source and error printer are replaced.
+    pub fn with_builder(builder: ReplBuilder) -> anyhow::Result<Self> {
+        let source = ScriptedSource::default();
+        let captured = Arc::new(Mutex::new(Captured::default()));
+
+        let errors = captured.clone();
+        let mut repl = builder
+            .line_source(CapturingSource {
+                source: source.clone(),
+                captured: captured.clone(),
+            })
+            .error_printer(move |err| {
+                let mut captured = lock(&errors);
+                let err = format!("{:#}", err);
+
+                captured.start_line();
+                captured.transcript.push_str(&err);
+                captured.transcript.push('\n');
+                captured.errors.push(err);
+            })
+            .build()?;
+        repl.print_output = true;
+
+        Ok(Self {
+            repl,
+            source,
+            captured,
+        })
+    }
+
+    /// Queues a line, as if the user typed it
+    pub fn feed(&mut self, line: impl Into<String>) -> &mut Self {
+        self.source.push_line(line);
+        self
+    }
+
+    /// Queues a Ctrl-C
+    pub fn interrupt(&mut self) -> &mut Self {
+        self.source.push(ReadLine::Interrupted);
+        self
+    }
+
+    /// Output written since the last call
+    pub fn take_output(&mut self) -> String {
+        std::mem::take(&mut lock(&self.captured).output)
+    }
+
+    /// Errors printed since the last call, formatted with their causes
+    pub fn take_errors(&mut self) -> Vec<String> {
+        std::mem::take(&mut lock(&self.captured).errors)
+    }
+
+    /// Everything read and printed so far, see the [module docs](self)
+    pub fn transcript(&self) -> String {
+        lock(&self.captured).transcript.clone()
+    }
+
+    /// Runs `handler` on the input lines of `golden` and panics if the
+    /// transcript differs from it
+    pub fn run_golden<R, H>(&mut self, handler: &mut H, golden: &str)
+    where
+        R: ReplCmd,
+        H: ReplHandler<R>,
+    {
+        for line in golden.lines() {
+            match line.strip_prefix('>') {
+                Some(" ^C") => self.interrupt(),
+                Some(input) => {
+                    self.feed(input.strip_prefix(' ').unwrap_or(input))
+                }
+                None => continue,
+            };
+        }
+
+        self.repl.run(handler).expect("Failed to run the repl");
+
+        let transcript = self.transcript();
+        assert!(
+            transcript == golden,
+            "Transcript differs\n--- expected\n{}\n--- actual\n{}",
+            golden,
+            transcript
+        );
+    }
+}
+
+impl Deref for ScriptedRepl {
+    type Target = Repl;
+
+    fn deref(&self) -> &Self::Target {
+        &self.repl
+    }
+}
+
+impl DerefMut for ScriptedRepl {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.repl
+    }
+}
+
+/// Records the lines read from the scripted source in the transcript
+struct CapturingSource {
+    source: ScriptedSource,
+    captured: Arc<Mutex<Captured>>,
+}
+
+impl LineSource for CapturingSource {
+    fn read_line(
+        &mut self,
+        prompt: &Prompt,
+        initial: Option<&str>,
+    ) -> io::Result<ReadLine> {
+        let line = self.source.read_line(prompt, initial)?;
+
+        let mut captured = lock(&self.captured);
+        let input = match &line {
+            ReadLine::Line(line) => line.as_str(),
+            ReadLine::Interrupted => "^C",
+            ReadLine::Eof => return Ok(line),
+        };
+
+        captured.start_line();
+        captured.transcript.push_str(&format!("> {}\n", input));
+
+        Ok(line)
+    }
+
+    fn add_history_entry(&mut self, line: &str) {
+        self.source.add_history_entry(line);
+    }
+
+    fn history(&self) -> Vec<String> {
+        self.source.history()
+    }
+
+    fn set_context(&mut self, context: LineContext) {
+        self.source.set_context(context);
+    }
+
+    fn external_printer(&mut self) -> Option<PrintHook> {
+        let captured = self.captured.clone();
+
+        Some(Box::new(move |msg| {
+            let mut captured = lock(&captured);
+            captured.transcript.push_str(&msg);
+            captured.output.push_str(&msg);
+            Ok(())
+        }))
+    }
+}
+
+fn lock(captured: &Mutex<Captured>) -> MutexGuard<'_, Captured> {
+    captured.lock().expect("Capture lock poisoned")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ControlFlow;
+
+    struct Echo(String);
+
+    impl ReplCmd for Echo {
+        fn help() -> &'static str {
+            "echo <0>\n"
+        }
+
+        fn command_names() -> &'static [&'static str] {
+            &["echo"]
+        }
+
+        fn parse<I>(_line: &str, mut parts: I) -> anyhow::Result<Self>
+        where
+            I: Iterator<Item = anyhow::Result<String>>,
+        {
+            match parts.next().transpose()?.as_deref() {
+                Some("echo") => Ok(Self(
+                    parts
+                        .next()
+                        .transpose()?
+                        .ok_or_else(|| anyhow::anyhow!("Missing field"))?,
+                )),
+                _ => anyhow::bail!("unrecognized command"),
+            }
+        }
+    }
+
+    #[test]
+    fn golden() {
+        let golden = "\
+> set x 1
+> echo $x
+1
+> ^C
+> ech
+Failed to parse command: unrecognized command
+> alias
+> alias e = echo
+> e 'a b'
+a b
+";
+        let mut repl = ScriptedRepl::new();
+        let printer = repl.printer();
+
+        repl.run_golden(
+            &mut |Echo(msg)| {
+                printer.println(msg)?;
+                Ok(ControlFlow::Continue)
+            },
+            golden,
+        );
+
+        assert_eq!(repl.take_output(), "1\na b\n");
+        assert_eq!(
+            repl.take_errors(),
+            vec!["Failed to parse command: unrecognized command"]
+        );
+    }
+
+    #[test]
+    fn execute() {
+        let mut repl = ScriptedRepl::new();
+        repl.feed("echo partial");
+
+        let outcome = repl.execute(|Echo(msg), output| {
+            use std::io::Write;
+
+            write!(output, "{}", msg)?;
+            Ok(msg)
+        });
+
+        assert_eq!(outcome.unwrap().command().as_deref(), Some("partial"));
+        assert_eq!(repl.take_output(), "partial\n");
+        assert_eq!(repl.transcript(), "> echo partial\npartial\n");
+    }
+}
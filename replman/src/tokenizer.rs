@@ -0,0 +1,416 @@
+use std::borrow::Cow;
+use std::ops::Range;
+
+use crate::variables::{self, Variables};
+
+/// Controls how lines are split into tokens
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TokenizerConfig {
+    /// Treat any Unicode whitespace (tabs, non-breaking spaces, ...) as a
+    /// separator, not only `' '`
+    pub unicode_whitespace: bool,
+    /// Treat a run of separators as a single one instead of producing empty
+    /// tokens
+    pub collapse_whitespace: bool,
+    /// Replace typographic quotes (`“ ” ‘ ’`) with their ASCII counterparts
+    pub normalize_quotes: bool,
+}
+
+impl Default for TokenizerConfig {
+    fn default() -> Self {
+        Self {
+            unicode_whitespace: true,
+            collapse_whitespace: true,
+            normalize_quotes: false,
+        }
+    }
+}
+
+impl TokenizerConfig {
+    fn is_separator(&self, c: char) -> bool {
+        if self.unicode_whitespace {
+            c.is_whitespace()
+        } else {
+            c == ' '
+        }
+    }
+
+    /// Applies the normalizations that have to happen before a line is split
+    pub(crate) fn normalize<'a>(&self, s: &'a str) -> Cow<'a, str> {
+        if self.normalize_quotes && s.contains(is_smart_quote) {
+            Cow::Owned(
+                s.chars()
+                    .map(|c| match c {
+                        '“' | '”' => '"',
+                        '‘' | '’' => '\'',
+                        c => c,
+                    })
+                    .collect(),
+            )
+        } else {
+            Cow::Borrowed(s)
+        }
+    }
+}
+
+fn is_smart_quote(c: char) -> bool {
+    matches!(c, '“' | '”' | '‘' | '’')
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Quoting {
+    None,
+    Single,
+    Double,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Token<'a> {
+    /// Token contents with the surrounding quotes removed
+    pub text: &'a str,
+    pub quoting: Quoting,
+}
+
+impl<'a> Token<'a> {
+    /// Expands variable references, single quoted tokens are kept literal
+    pub fn expand(&self, variables: &Variables) -> anyhow::Result<String> {
+        match self.quoting {
+            Quoting::Single => Ok(self.text.to_string()),
+            Quoting::None | Quoting::Double => variables.expand(self.text),
+        }
+    }
+}
+
+/// Output redirection at the end of a line
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Redirect<'a> {
+    /// `cmd | shell command`
+    Pipe(&'a str),
+    /// `cmd > file`
+    Truncate(&'a str),
+    /// `cmd >> file`
+    Append(&'a str),
+}
+
+/// Splits off the first unquoted `|`, `>` or `>>` and everything after it
+pub(crate) fn split_redirect(s: &str) -> (&str, Option<Redirect<'_>>) {
+    let mut is_in_double_qoutes = false;
+    let mut is_in_single_quotes = false;
+
+    for (idx, c) in s.char_indices() {
+        match c {
+            '"' if !is_in_single_quotes => {
+                is_in_double_qoutes = !is_in_double_qoutes
+            }
+            '\'' if !is_in_double_qoutes => {
+                is_in_single_quotes = !is_in_single_quotes
+            }
+            '|' | '>' if !is_in_double_qoutes && !is_in_single_quotes => {
+                let cmd = s[..idx].trim_end();
+                let rest = &s[idx + 1..];
+
+                let redirect = match (c, rest.strip_prefix('>')) {
+                    ('|', _) => Redirect::Pipe(rest.trim()),
+                    (_, Some(rest)) => Redirect::Append(rest.trim()),
+                    (_, None) => Redirect::Truncate(rest.trim()),
+                };
+
+                return (cmd, Some(redirect));
+            }
+            _ => (),
+        }
+    }
+
+    (s, None)
+}
+
+/// Quotes `arg` if needed, so that it's read back as a single token
+///
+/// Single quotes are preferred, as variables aren't expanded in them.
+/// Quotes and `$` can't be escaped, so an argument containing both kinds of
+/// quotes, or a single quote and a variable reference, is an error.
+pub fn quote_arg(arg: &str) -> anyhow::Result<Cow<'_, str>> {
+    let plain = !arg.is_empty()
+        && !arg.contains(|c: char| {
+            c.is_whitespace() || matches!(c, '"' | '\'' | '$' | '|' | '>')
+        });
+
+    if plain {
+        Ok(Cow::Borrowed(arg))
+    } else if !arg.contains('\'') {
+        Ok(Cow::Owned(format!("'{}'", arg)))
+    } else if arg.contains('"') {
+        anyhow::bail!(
+            "{:?} can't be quoted, it contains both kinds of quotes",
+            arg
+        )
+    } else if !variables::references(arg).is_empty() {
+        // It would be expanded in double quotes
+        anyhow::bail!(
+            "{:?} can't be quoted, it contains a single quote and a variable",
+            arg
+        )
+    } else {
+        Ok(Cow::Owned(format!("\"{}\"", arg)))
+    }
+}
+
+pub(crate) fn split_string_unescape(
+    s: &str,
+    config: TokenizerConfig,
+) -> impl Iterator<Item = anyhow::Result<String>> + '_ {
+    split_string(s, config)
+        .map(|token| token.map(|token| token.text.to_string()))
+}
+
+pub(crate) fn split_string_expand<'a>(
+    s: &'a str,
+    variables: &'a Variables,
+    config: TokenizerConfig,
+) -> impl Iterator<Item = anyhow::Result<String>> + 'a {
+    split_string(s, config).map(move |token| token?.expand(variables))
+}
+
+pub(crate) fn split_string(
+    s: &str,
+    config: TokenizerConfig,
+) -> impl Iterator<Item = anyhow::Result<Token<'_>>> {
+    token_spans(s, config).map(move |span| span.map(|span| unescape(&s[span])))
+}
+
+/// Byte ranges of the tokens of `s`, including their quotes
+///
+/// Ends after the first error.
+pub(crate) fn token_spans(
+    s: &str,
+    config: TokenizerConfig,
+) -> impl Iterator<Item = anyhow::Result<Range<usize>>> + '_ {
+    let skip_separators = move |start: usize| {
+        if config.collapse_whitespace {
+            s.len()
+                - s[start..]
+                    .trim_start_matches(|c| config.is_separator(c))
+                    .len()
+        } else {
+            start
+        }
+    };
+
+    let mut start = skip_separators(0);
+
+    std::iter::from_fn(move || {
+        if start == s.len() {
+            return None;
+        }
+
+        let span = match find_next_unescaped_space(&s[start..], config) {
+            Ok(Some((x, separator))) => {
+                let span = start..start + x;
+                start = skip_separators(span.end + separator.len_utf8());
+
+                span
+            }
+            Ok(None) => {
+                let span = start..s.len();
+                start = s.len();
+
+                span
+            }
+            Err(err) => {
+                start = s.len();
+                return Some(Err(err));
+            }
+        };
+
+        Some(Ok(span))
+    })
+}
+
+fn unescape(s: &str) -> Token<'_> {
+    let quoting = if s.len() >= 2 && s.starts_with('"') && s.ends_with('"') {
+        Quoting::Double
+    } else if s.len() >= 2 && s.starts_with('\'') && s.ends_with('\'') {
+        Quoting::Single
+    } else {
+        Quoting::None
+    };
+
+    match quoting {
+        Quoting::None => Token { text: s, quoting },
+        _ => Token {
+            text: &s[1..s.len() - 1],
+            quoting,
+        },
+    }
+}
+
+fn find_next_unescaped_space(
+    s: &str,
+    config: TokenizerConfig,
+) -> anyhow::Result<Option<(usize, char)>> {
+    let mut is_in_double_qoutes = false;
+    let mut is_in_single_quotes = false;
+
+    let mut previous_was_quoted = false;
+    for (idx, c) in s.char_indices() {
+        if previous_was_quoted && !config.is_separator(c) {
+            return Err(anyhow::anyhow!(
+                "Invalid command fragment, expected a space or end of string, found '{}'",
+                c
+            ));
+        }
+
+        match c {
+            '"' if !is_in_double_qoutes && !is_in_single_quotes => {
+                is_in_double_qoutes = true
+            }
+            '"' if is_in_double_qoutes => {
+                is_in_double_qoutes = false;
+                previous_was_quoted = true;
+            }
+            '\'' if !is_in_double_qoutes && !is_in_single_quotes => {
+                is_in_single_quotes = true
+            }
+            '\'' if is_in_single_quotes => {
+                is_in_single_quotes = false;
+                previous_was_quoted = true;
+            }
+            _ => previous_was_quoted = false,
+        }
+
+        if is_in_double_qoutes || is_in_single_quotes {
+            continue;
+        }
+
+        if config.is_separator(c) {
+            return Ok(Some((idx, c)));
+        }
+    }
+
+    Ok(None)
+}
+
+#[cfg(test)]
+mod tests {
+    use test_case::test_case;
+
+    use super::*;
+
+    #[test_case("Hello", vec!["Hello"] ; "Single item")]
+    #[test_case("Hello World!", vec!["Hello", "World!"] ; "Two items")]
+    #[test_case("", vec![] ; "Empty")]
+    fn basic(s: &str, exp: Vec<&str>) {
+        let actual: Vec<_> = split_string_unescape(s, Default::default())
+            .map(Result::unwrap)
+            .collect();
+        assert_eq!(actual, exp);
+    }
+
+    #[test_case(r#""Hello, World!""#, vec!["Hello, World!"] ; "Single - double quotes")]
+    #[test_case(r#"'Hello, World!'"#, vec!["Hello, World!"] ; "Single - single quotes")]
+    #[test_case(r#"'Hello, World!' "What is going on?""#, vec!["Hello, World!", "What is going on?"] ; "Two items - mixed")]
+    #[test_case(r#""" "" """#, vec!["", "", ""] ; "Sequence of double quotes")]
+    fn escaped(s: &str, exp: Vec<&str>) {
+        let actual: Vec<_> = split_string_unescape(s, Default::default())
+            .map(Result::unwrap)
+            .collect();
+        assert_eq!(actual, exp);
+    }
+
+    #[test_case("Hello\u{a0}World!", vec!["Hello", "World!"] ; "Non-breaking space")]
+    #[test_case("Hello\tWorld!", vec!["Hello", "World!"] ; "Tab")]
+    #[test_case("Hello \t  World! ", vec!["Hello", "World!"] ; "Repeated separators")]
+    #[test_case("\"Hello\"\u{a0}World!", vec!["Hello", "World!"] ; "Quoted followed by non-breaking space")]
+    fn whitespace(s: &str, exp: Vec<&str>) {
+        let actual: Vec<_> = split_string_unescape(s, Default::default())
+            .map(Result::unwrap)
+            .collect();
+        assert_eq!(actual, exp);
+    }
+
+    #[test_case("Hello\u{a0}World!", vec!["Hello\u{a0}World!"] ; "Non-breaking space")]
+    #[test_case("Hello  World!", vec!["Hello", "", "World!"] ; "Repeated separators")]
+    fn whitespace_disabled(s: &str, exp: Vec<&str>) {
+        let config = TokenizerConfig {
+            unicode_whitespace: false,
+            collapse_whitespace: false,
+            ..Default::default()
+        };
+
+        let actual: Vec<_> = split_string_unescape(s, config)
+            .map(Result::unwrap)
+            .collect();
+        assert_eq!(actual, exp);
+    }
+
+    #[test_case("str “Hello, World!”", "str \"Hello, World!\"" ; "Double")]
+    #[test_case("str ‘Hello’", "str 'Hello'" ; "Single")]
+    fn normalize_quotes(s: &str, exp: &str) {
+        let config = TokenizerConfig {
+            normalize_quotes: true,
+            ..Default::default()
+        };
+
+        assert_eq!(config.normalize(s), exp);
+        assert_eq!(TokenizerConfig::default().normalize(s), s);
+    }
+
+    #[test_case("plain", "plain" ; "Plain")]
+    #[test_case("", "''" ; "Empty")]
+    #[test_case("a b", "'a b'" ; "Space")]
+    #[test_case("$x|y", "'$x|y'" ; "Special characters")]
+    #[test_case("it's", "\"it's\"" ; "Single quote")]
+    #[test_case("say \"hi\"", "'say \"hi\"'" ; "Double quotes")]
+    #[test_case("it's $5", "\"it's $5\"" ; "Single quote and a dollar sign")]
+    fn quotes_args(arg: &str, exp: &str) {
+        let quoted = quote_arg(arg).unwrap();
+        assert_eq!(quoted, exp);
+
+        let line = format!("{} {}", quoted, quoted);
+        let actual: Vec<_> = split_string_unescape(&line, Default::default())
+            .map(Result::unwrap)
+            .collect();
+        assert_eq!(actual, vec![arg, arg]);
+    }
+
+    #[test_case("it's \"x\"" ; "Both kinds of quotes")]
+    #[test_case("it's $HOME" ; "Single quote and a variable")]
+    #[test_case("it's ${HOME" ; "Single quote and an unterminated variable")]
+    fn unquotable_args(arg: &str) {
+        assert!(quote_arg(arg).is_err());
+    }
+
+    #[test_case("list", ("list", None) ; "None")]
+    #[test_case("list | grep foo | wc -l", ("list", Some(Redirect::Pipe("grep foo | wc -l"))) ; "Pipe")]
+    #[test_case("list > out.txt", ("list", Some(Redirect::Truncate("out.txt"))) ; "Truncate")]
+    #[test_case("list >> out.txt", ("list", Some(Redirect::Append("out.txt"))) ; "Append")]
+    #[test_case("str 'a | b' \"c > d\"", ("str 'a | b' \"c > d\"", None) ; "Quoted")]
+    fn redirect(s: &str, exp: (&str, Option<Redirect>)) {
+        assert_eq!(split_redirect(s), exp);
+    }
+
+    #[test_case("set x 'a b'", vec![0..3, 4..5, 6..11] ; "Quoted")]
+    #[test_case("  str   x ", vec![2..5, 8..9] ; "Surrounding whitespace")]
+    #[test_case("str \"a\"b c", vec![0..3] ; "Stops at errors")]
+    fn spans(s: &str, exp: Vec<Range<usize>>) {
+        let actual: Vec<_> = token_spans(s, Default::default())
+            .filter_map(Result::ok)
+            .collect();
+        assert_eq!(actual, exp);
+    }
+
+    #[test_case("echo $name", vec!["echo", "value"] ; "Unquoted")]
+    #[test_case("echo ${name}s", vec!["echo", "values"] ; "Braced")]
+    #[test_case(r#"echo "$name and $name""#, vec!["echo", "value and value"] ; "Double quotes")]
+    #[test_case("echo '$name'", vec!["echo", "$name"] ; "Single quotes are literal")]
+    fn expanded(s: &str, exp: Vec<&str>) {
+        let mut variables = Variables::default();
+        variables.set("name", "value");
+
+        let actual: Vec<_> =
+            split_string_expand(s, &variables, Default::default())
+                .map(Result::unwrap)
+                .collect();
+        assert_eq!(actual, exp);
+    }
+}
@@ -0,0 +1,172 @@
+use std::collections::BTreeMap;
+use std::ops::Range;
+
+/// Variables available for `$name` and `${name}` interpolation
+///
+/// Lookups fall back to the process environment when a variable isn't
+/// defined in the REPL itself.
+#[derive(Debug, Clone, Default)]
+pub struct Variables {
+    values: BTreeMap<String, String>,
+}
+
+impl Variables {
+    pub fn set(&mut self, name: impl Into<String>, value: impl Into<String>) {
+        self.values.insert(name.into(), value.into());
+    }
+
+    pub fn unset(&mut self, name: &str) -> Option<String> {
+        self.values.remove(name)
+    }
+
+    pub fn get(&self, name: &str) -> Option<String> {
+        self.values
+            .get(name)
+            .cloned()
+            .or_else(|| std::env::var(name).ok())
+    }
+
+    /// Iterates over the variables defined in the REPL, without the
+    /// environment
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.values.iter().map(|(k, v)| (k.as_str(), v.as_str()))
+    }
+
+    pub fn expand(&self, s: &str) -> anyhow::Result<String> {
+        let mut ret = String::with_capacity(s.len());
+        let mut rest = s;
+
+        while let Some(idx) = rest.find('$') {
+            ret.push_str(&rest[..idx]);
+            rest = &rest[idx + 1..];
+
+            let (name, remaining) = if let Some(braced) = rest.strip_prefix('{')
+            {
+                let end = braced.find('}').ok_or_else(|| {
+                    anyhow::anyhow!(
+                        "Unterminated variable reference '${{{}'",
+                        braced
+                    )
+                })?;
+
+                (&braced[..end], &braced[end + 1..])
+            } else {
+                let end =
+                    rest.find(|c: char| !is_name_char(c)).unwrap_or(rest.len());
+
+                (&rest[..end], &rest[end..])
+            };
+
+            if !is_valid_name(name) {
+                // Not a variable reference, keep the dollar sign as is
+                ret.push('$');
+                continue;
+            }
+
+            let value = self.get(name).ok_or_else(|| {
+                anyhow::anyhow!("Undefined variable '{}'", name)
+            })?;
+
+            ret.push_str(&value);
+            rest = remaining;
+        }
+
+        ret.push_str(rest);
+
+        Ok(ret)
+    }
+}
+
+/// Byte ranges of the variable references in `s`, including the `$` and the
+/// braces
+pub(crate) fn references(s: &str) -> Vec<Range<usize>> {
+    let mut ret = vec![];
+    let mut start = 0;
+
+    while let Some(idx) = s[start..].find('$') {
+        let dollar = start + idx;
+        let rest = &s[dollar + 1..];
+        start = dollar + 1;
+
+        let len = if let Some(braced) = rest.strip_prefix('{') {
+            // Unterminated references are included, they are being typed
+            braced.find('}').map_or(rest.len(), |end| end + 2)
+        } else {
+            let end =
+                rest.find(|c: char| !is_name_char(c)).unwrap_or(rest.len());
+
+            if !is_valid_name(&rest[..end]) {
+                continue;
+            }
+
+            end
+        };
+
+        start += len;
+        ret.push(dollar..start);
+    }
+
+    ret
+}
+
+pub(crate) fn is_valid_name(name: &str) -> bool {
+    let mut chars = name.chars();
+
+    match chars.next() {
+        Some(c) if c.is_ascii_alphabetic() || c == '_' => {
+            chars.all(is_name_char)
+        }
+        _ => false,
+    }
+}
+
+fn is_name_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_'
+}
+
+#[cfg(test)]
+mod tests {
+    use test_case::test_case;
+
+    use super::*;
+
+    fn variables() -> Variables {
+        let mut variables = Variables::default();
+        variables.set("host", "localhost");
+        variables.set("port", "8080");
+        variables
+    }
+
+    #[test_case("$host", "localhost" ; "Plain")]
+    #[test_case("${host}:${port}", "localhost:8080" ; "Braced")]
+    #[test_case("$host:$port", "localhost:8080" ; "Separated")]
+    #[test_case("cost: 5$", "cost: 5$" ; "Trailing dollar")]
+    #[test_case("$ 1", "$ 1" ; "Dollar followed by space")]
+    #[test_case("no variables", "no variables" ; "No variables")]
+    fn expand(s: &str, exp: &str) {
+        assert_eq!(variables().expand(s).unwrap(), exp);
+    }
+
+    #[test]
+    fn falls_back_to_env() {
+        std::env::set_var("REPLMAN_TEST_VARIABLE", "from env");
+
+        assert_eq!(
+            variables().expand("$REPLMAN_TEST_VARIABLE").unwrap(),
+            "from env"
+        );
+    }
+
+    #[test_case("$undefined_variable" ; "Undefined")]
+    #[test_case("${host" ; "Unterminated")]
+    fn expand_errors(s: &str) {
+        assert!(variables().expand(s).is_err());
+    }
+
+    #[test_case("$host:${port}", vec![0..5, 6..13] ; "Plain and braced")]
+    #[test_case("cost: 5$ $1", vec![] ; "Not references")]
+    #[test_case("a ${hos", vec![2..7] ; "Unterminated")]
+    fn reference_spans(s: &str, exp: Vec<Range<usize>>) {
+        assert_eq!(references(s), exp);
+    }
+}
@@ -1,9 +1,17 @@
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
 use difference::assert_diff;
 use replman::prelude::*;
+use replman::{
+    HistoryPolicy, InterruptBehavior, LineSource, Prompt, ReadLine,
+    ScriptedSource,
+};
 use test_case::test_case;
 
 #[derive(PartialEq, Debug, ReplCmd)]
 #[replman(rename_all = "snake_case")]
+#[replman(display)]
 enum Command {
     /// Displays help
     Help,
@@ -12,6 +20,7 @@ enum Command {
     /// make sure to use it
     #[replman(alias = "exit")]
     #[replman(starts_with = "q")]
+    #[replman(on_eof)]
     Quit,
     /// Just here to mess with quit starts_with
     Quote,
@@ -36,6 +45,13 @@ enum Command {
         with_default_explicit: u32,
     },
     Str(String),
+    /// Evaluates an expression
+    #[replman(raw)]
+    Eval(String),
+    #[replman(raw)]
+    Sql {
+        query: String,
+    },
 }
 
 #[test]
@@ -53,6 +69,8 @@ fn help_test() {
                                                   Lorem ipsum
         with_default_explicit <with_default_explicit> - A command with a single argument that has a default value
         str <0>
+        eval <0...> - Evaluates an expression
+        sql <query...>
     "#};
 
     assert_diff!(HELP, Command::help(), "", 0);
@@ -131,3 +149,829 @@ fn escape_strings(s: &str, exp: Command) {
     let cmd = Command::parse_str(s).unwrap();
     assert_eq!(exp, cmd);
 }
+
+#[test]
+fn command_names() {
+    assert_eq!(
+        Command::command_names(),
+        &[
+            "help",
+            "quit",
+            "exit",
+            "quote",
+            "named_args",
+            "unnamed_args",
+            "optional_arg",
+            "with_default_value",
+            "with_default_explicit",
+            "str",
+            "eval",
+            "sql",
+        ]
+    );
+}
+
+#[test]
+fn on_eof() {
+    assert_eq!(Command::on_eof(), Some(Command::Quit));
+}
+
+#[test]
+fn help_lists_runtime_aliases() {
+    let mut repl = Repl::new();
+    repl.add_alias("na", "named_args 1");
+
+    let help = repl.help::<Command>();
+
+    assert!(help.starts_with(Command::help()));
+    assert!(help.ends_with("Aliases:\nna = named_args 1\n"));
+}
+
+#[test_case("eval 1 + 2", Command::Eval("1 + 2".to_string()) ; "simple")]
+#[test_case("eval  1 +  \"2\"  'three'", Command::Eval("1 +  \"2\"  'three'".to_string()) ; "whitespace and quotes")]
+#[test_case("eval \"a\"b", Command::Eval("\"a\"b".to_string()) ; "invalid tokens")]
+#[test_case("eval", Command::Eval("".to_string()) ; "empty")]
+#[test_case("sql SELECT * FROM t WHERE name = 'x'", Command::Sql {
+    query: "SELECT * FROM t WHERE name = 'x'".to_string()
+} ; "named")]
+fn raw(s: &str, exp: Command) {
+    let cmd = Command::parse_str(s).unwrap();
+    assert_eq!(exp, cmd);
+}
+
+#[test]
+fn empty_line_is_an_error() {
+    assert!(Command::parse_str("").is_err());
+}
+
+#[test]
+fn script() {
+    let script = "named_args 1 2\n# a comment\n\nset x 3\nunnamed_args $x 4\n";
+    let mut repl = Repl::from_reader(std::io::Cursor::new(script), "test.repl");
+
+    let mut commands = vec![];
+    while let ReadOutcome::Command(cmd) =
+        repl.read_command::<Command>().unwrap()
+    {
+        if cmd == Command::Quit {
+            break;
+        }
+
+        commands.push(cmd);
+    }
+
+    assert_eq!(
+        commands,
+        vec![
+            Command::NamedArgs { left: 1, right: 2 },
+            Command::UnnamedArgs(3, 4)
+        ]
+    );
+}
+
+#[test]
+fn script_stops_on_error() {
+    let script = "quote\n\nnamed_args 1 x\nquote\n";
+    let mut repl = Repl::from_reader(std::io::Cursor::new(script), "test.repl");
+
+    assert_eq!(
+        repl.read_command::<Command>().unwrap(),
+        ReadOutcome::Command(Command::Quote)
+    );
+
+    let err = repl.read_command::<Command>().unwrap_err();
+    assert!(format!("{:#}", err)
+        .starts_with("test.repl:3: Failed to parse command: "));
+}
+
+#[test]
+fn script_continues_on_error() {
+    let script = "named_args 1 x\nquote\n";
+    let mut repl = Repl::builder()
+        .script(std::io::Cursor::new(script), "test.repl")
+        .stop_on_error(false)
+        .error_printer(|_| ())
+        .build()
+        .unwrap();
+
+    assert_eq!(
+        repl.read_command::<Command>().unwrap(),
+        ReadOutcome::Command(Command::Quote)
+    );
+}
+
+#[test]
+fn source() {
+    let tmp = tempfile::tempdir().unwrap();
+    let dir = tmp.path();
+    let inner = dir.join("inner.repl");
+    let outer = dir.join("outer.repl");
+    let cycle = dir.join("cycle.repl");
+    let failing = dir.join("failing.repl");
+
+    std::fs::write(&inner, "unnamed_args 3 4\n").unwrap();
+    std::fs::write(
+        &outer,
+        format!("named_args 1 2\nsource {}\n", inner.display()),
+    )
+    .unwrap();
+    std::fs::write(&cycle, format!("source {}\n", outer.display())).unwrap();
+    std::fs::write(
+        &failing,
+        format!("source {}\nnamed_args x\n", inner.display()),
+    )
+    .unwrap();
+
+    let script = format!("source {}\nquote\n", outer.display());
+    let mut repl = Repl::from_reader(std::io::Cursor::new(script), "main");
+
+    for exp in [
+        Command::NamedArgs { left: 1, right: 2 },
+        Command::UnnamedArgs(3, 4),
+        Command::Quote,
+    ] {
+        assert_eq!(
+            repl.read_command::<Command>().unwrap(),
+            ReadOutcome::Command(exp)
+        );
+    }
+
+    // outer.repl sourcing cycle.repl, which sources outer.repl again
+    std::fs::write(&outer, format!("source {}\n", cycle.display())).unwrap();
+    let script = format!("source {}\n", outer.display());
+    let mut repl = Repl::from_reader(std::io::Cursor::new(script), "main");
+
+    let err = repl.read_command::<Command>().unwrap_err();
+    assert_eq!(
+        format!("{:#}", err),
+        format!(
+            "{cycle}:1, sourced from {outer}:1, sourced from main:1: \
+             Include cycle: {outer} -> {cycle} -> {outer}",
+            outer = outer.display(),
+            cycle = cycle.display(),
+        )
+    );
+
+    // Errors of builtins aren't parse errors
+    let missing = dir.join("missing.repl");
+    let script = format!("source {}\n", missing.display());
+    let mut repl = Repl::from_reader(std::io::Cursor::new(script), "main");
+
+    let err = repl.read_command::<Command>().unwrap_err();
+    assert!(format!("{:#}", err)
+        .starts_with(&format!("main:1: Failed to open {}", missing.display())));
+
+    let script = format!("source {}\n", failing.display());
+    let mut repl = Repl::from_reader(std::io::Cursor::new(script), "main");
+
+    assert_eq!(
+        repl.read_command::<Command>().unwrap(),
+        ReadOutcome::Command(Command::UnnamedArgs(3, 4))
+    );
+    let err = repl.read_command::<Command>().unwrap_err();
+    assert!(format!("{:#}", err).starts_with(&format!(
+        "{}:2, sourced from main:1: Failed to parse command: ",
+        failing.display()
+    )));
+}
+
+#[test]
+fn rc_file() {
+    let tmp = tempfile::tempdir().unwrap();
+    let dir = tmp.path();
+    let rc_file = dir.join("replrc");
+    let failing = dir.join("failingrc");
+
+    std::fs::write(&rc_file, "alias na = named_args 1\nset x 2\nna $x\n")
+        .unwrap();
+    std::fs::write(&failing, "set x 2\n\nunnamed_args $x\n").unwrap();
+
+    let mut repl = Repl::builder()
+        .script(std::io::Cursor::new("na 3\n"), "main")
+        .rc_file(&rc_file)
+        .build()
+        .unwrap();
+
+    for exp in [
+        Command::NamedArgs { left: 1, right: 2 },
+        Command::NamedArgs { left: 1, right: 3 },
+    ] {
+        assert_eq!(
+            repl.read_command::<Command>().unwrap(),
+            ReadOutcome::Command(exp)
+        );
+    }
+
+    let mut repl = Repl::builder()
+        .script(std::io::Cursor::new("quote\n"), "main")
+        .rc_file(&failing)
+        .stop_on_error(false)
+        .build()
+        .unwrap();
+
+    let err = repl.read_command::<Command>().unwrap_err();
+    assert!(format!("{:#}", err).starts_with(&format!(
+        "{}:3: Failed to parse command: ",
+        failing.display()
+    )));
+
+    let mut repl = Repl::builder()
+        .script(std::io::Cursor::new("quote\n"), "main")
+        .rc_file(dir.join("missing"))
+        .build()
+        .unwrap();
+
+    assert_eq!(
+        repl.read_command::<Command>().unwrap(),
+        ReadOutcome::Command(Command::Quote)
+    );
+}
+
+#[test]
+fn commands() {
+    let usage = |name: &str| {
+        let info = Command::commands()
+            .iter()
+            .find(|info| info.is_named(name))
+            .unwrap();
+
+        let words: Vec<_> = std::iter::once(info.name.to_string())
+            .chain(info.args.iter().map(ToString::to_string))
+            .collect();
+        words.join(" ")
+    };
+
+    assert_eq!(usage("exit"), "quit");
+    assert_eq!(
+        usage("named_args"),
+        "named_args <left: usize> <right: usize>"
+    );
+    assert_eq!(usage("unnamed_args"), "unnamed_args <0: usize> <1: usize>");
+    assert_eq!(
+        usage("optional_arg"),
+        "optional_arg <first_arg: String> [optional_arg: u32]"
+    );
+    assert_eq!(
+        usage("with_default_value"),
+        "with_default_value [with_default_value: u32]"
+    );
+    assert_eq!(usage("sql"), "sql <query...>");
+}
+
+#[test_case(Command::Quit, "quit" ; "Unit")]
+#[test_case(Command::NamedArgs { left: 1, right: 2 }, "named_args 1 2" ; "Named")]
+#[test_case(Command::UnnamedArgs(0, 0), "unnamed_args 0 0" ; "Unnamed")]
+#[test_case(
+    Command::OptionalArg { first_arg: "a b".to_string(), optional_arg: None },
+    "optional_arg 'a b'" ;
+    "None omitted"
+)]
+#[test_case(
+    Command::OptionalArg { first_arg: "it's".to_string(), optional_arg: Some(3) },
+    "optional_arg \"it's\" 3" ;
+    "Quoted"
+)]
+#[test_case(
+    Command::WithDefaultValue { with_default_value: 0 },
+    "with_default_value" ;
+    "Default omitted"
+)]
+#[test_case(
+    Command::WithDefaultExplicit { with_default_explicit: 42 },
+    "with_default_explicit" ;
+    "Explicit default omitted"
+)]
+#[test_case(
+    Command::WithDefaultExplicit { with_default_explicit: 7 },
+    "with_default_explicit 7" ;
+    "Explicit default differs"
+)]
+#[test_case(Command::Str(String::new()), "str ''" ; "Empty")]
+#[test_case(Command::Eval("1 +  2".to_string()), "eval 1 +  2" ; "Raw")]
+fn to_command_line(cmd: Command, expected: &str) {
+    assert_eq!(cmd.try_to_command_line().unwrap(), expected);
+    assert_eq!(cmd.to_command_line(), expected);
+    assert_eq!(cmd.to_string(), expected);
+    assert_eq!(Command::parse_str(expected).unwrap(), cmd);
+}
+
+#[derive(PartialEq, Debug, ReplCmd)]
+#[replman(rename_all = "snake_case")]
+enum TwoOptions {
+    Two { a: Option<u32>, b: Option<u32> },
+}
+
+#[test_case(Some(1), Some(2), Some("two 1 2") ; "Both")]
+#[test_case(Some(1), None, Some("two 1") ; "Trailing None")]
+#[test_case(None, None, Some("two") ; "Neither")]
+#[test_case(None, Some(5), None ; "None before an argument")]
+fn to_command_line_options(a: Option<u32>, b: Option<u32>, exp: Option<&str>) {
+    let cmd = TwoOptions::Two { a, b };
+
+    match exp {
+        Some(exp) => {
+            assert_eq!(cmd.try_to_command_line().unwrap(), exp);
+            assert_eq!(TwoOptions::parse_str(exp).unwrap(), cmd);
+        }
+        None => {
+            let err = cmd.try_to_command_line().unwrap_err();
+            assert_eq!(
+                err.to_string(),
+                "Argument 'a' of 'two' is None, but arguments after it aren't"
+            );
+            assert_eq!(cmd.to_command_line(), "two '' 5");
+        }
+    }
+}
+
+/// Neither `ToArg` nor `PartialEq`
+#[derive(Debug, Default)]
+enum Level {
+    #[default]
+    Info,
+    Debug,
+}
+
+impl replman::ReplCmdParse for Level {
+    fn parse(item: Option<&str>) -> anyhow::Result<Self> {
+        match item {
+            Some("info") => Ok(Self::Info),
+            Some("debug") => Ok(Self::Debug),
+            _ => anyhow::bail!("Invalid level"),
+        }
+    }
+
+    fn parse_default(s: &str) -> anyhow::Result<Self> {
+        Self::parse(Some(s))
+    }
+}
+
+#[derive(Debug, ReplCmd)]
+#[replman(rename_all = "snake_case", display)]
+enum CustomArg {
+    Log {
+        #[replman(default)]
+        level: Level,
+    },
+}
+
+#[test]
+fn to_command_line_without_to_arg() {
+    let cmd = CustomArg::Log {
+        level: Level::Debug,
+    };
+    let err = cmd.try_to_command_line().unwrap_err();
+    assert!(
+        err.to_string().contains("doesn't implement ToArg"),
+        "{}",
+        err
+    );
+    // Display still writes what it can
+    assert_eq!(cmd.to_command_line(), "log ?");
+    assert_eq!(cmd.to_string(), "log ?");
+
+    // Defaults of types without `PartialEq` are written too
+    assert!(CustomArg::Log { level: Level::Info }
+        .try_to_command_line()
+        .is_err());
+}
+
+/// `Display` is only derived with `#[replman(display)]`
+#[derive(Debug, ReplCmd)]
+enum OwnDisplay {
+    Ping,
+}
+
+impl std::fmt::Display for OwnDisplay {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("ping!")
+    }
+}
+
+#[test]
+fn own_display() {
+    assert_eq!(OwnDisplay::Ping.to_string(), "ping!");
+    assert_eq!(OwnDisplay::Ping.to_command_line(), "Ping");
+    assert!(matches!(
+        OwnDisplay::parse_str("Ping"),
+        Ok(OwnDisplay::Ping)
+    ));
+}
+
+#[test]
+fn shared_repl() {
+    let script = "set x 1\nunnamed_args $x 2\nquote\n";
+    replman::with_repl(|repl| {
+        *repl = Repl::from_reader(std::io::Cursor::new(script), "shared");
+    });
+
+    // Variables carry over between calls
+    assert_eq!(
+        read_command::<Command>().unwrap(),
+        ReadOutcome::Command(Command::UnnamedArgs(1, 2))
+    );
+    assert_eq!(
+        read_command::<Command>().unwrap(),
+        ReadOutcome::Command(Command::Quote)
+    );
+}
+
+#[test]
+fn line_source() {
+    let source = ScriptedSource::new(["quote", "!1", "bogus"]);
+    source.push(ReadLine::Interrupted);
+    source.push_line("history");
+
+    let mut repl = Repl::builder()
+        .line_source(source.clone())
+        .history_policy(HistoryPolicy::All)
+        .interrupt_behavior(InterruptBehavior::Return)
+        .build()
+        .unwrap();
+
+    assert_eq!(
+        repl.read_command::<Command>().unwrap(),
+        ReadOutcome::Command(Command::Quote)
+    );
+    assert_eq!(
+        repl.read_command::<Command>().unwrap(),
+        ReadOutcome::Command(Command::Quote)
+    );
+    assert_eq!(
+        repl.read_command::<Command>().unwrap(),
+        ReadOutcome::Interrupted
+    );
+    // `history` is a builtin, then the lines run out
+    assert_eq!(
+        repl.read_command::<Command>().unwrap(),
+        ReadOutcome::Command(Command::Quit)
+    );
+    assert_eq!(source.history(), vec!["quote", "quote", "bogus", "history"]);
+}
+
+/// The prompt and `initial` text of the reads of a [`ReadsSource`]
+type Reads = Arc<Mutex<Vec<(Prompt, Option<String>)>>>;
+
+/// Remembers the prompt and `initial` text of every read, and keeps its
+/// history in a plain file
+struct ReadsSource {
+    source: ScriptedSource,
+    reads: Reads,
+}
+
+impl ReadsSource {
+    fn new(lines: &[&str]) -> (Self, Reads) {
+        let reads = Arc::new(Mutex::new(vec![]));
+        let source = Self {
+            source: ScriptedSource::new(lines.iter().copied()),
+            reads: reads.clone(),
+        };
+
+        (source, reads)
+    }
+}
+
+impl LineSource for ReadsSource {
+    fn read_line(
+        &mut self,
+        prompt: &Prompt,
+        initial: Option<&str>,
+    ) -> std::io::Result<ReadLine> {
+        let mut reads = self.reads.lock().unwrap();
+        reads.push((prompt.clone(), initial.map(str::to_string)));
+        self.source.read_line(prompt, initial)
+    }
+
+    fn add_history_entry(&mut self, line: &str) {
+        self.source.add_history_entry(line);
+    }
+
+    fn history(&self) -> Vec<String> {
+        self.source.history()
+    }
+
+    fn load_history(&mut self, path: &Path) -> std::io::Result<()> {
+        for line in std::fs::read_to_string(path)?.lines() {
+            self.add_history_entry(line);
+        }
+
+        Ok(())
+    }
+
+    fn append_history(&mut self, path: &Path) -> std::io::Result<()> {
+        let history: String = self
+            .history()
+            .iter()
+            .map(|line| format!("{}\n", line))
+            .collect();
+        std::fs::write(path, history)
+    }
+}
+
+#[test]
+fn builder_settings() {
+    let tmp = tempfile::tempdir().unwrap();
+    let history_file = tmp.path().join("history.txt");
+    std::fs::write(&history_file, "earlier\n").unwrap();
+
+    let errors = Arc::new(Mutex::new(vec![]));
+    let printed = errors.clone();
+    let (source, reads) = ReadsSource::new(&["", " quote", "named_args 1 2"]);
+    let mut repl = Repl::builder()
+        .line_source(source)
+        .prompt("db> ")
+        .skip_blank_lines(false)
+        .history_ignore_space(true)
+        .history_file(&history_file)
+        .error_printer(move |err| {
+            printed.lock().unwrap().push(format!("{:#}", err))
+        })
+        .build()
+        .unwrap();
+
+    // The blank line is parsed and fails, the error goes to the printer
+    assert_eq!(
+        repl.read_command::<Command>().unwrap(),
+        ReadOutcome::Command(Command::Quote)
+    );
+    assert_eq!(
+        repl.read_command::<Command>().unwrap(),
+        ReadOutcome::Command(Command::NamedArgs { left: 1, right: 2 })
+    );
+
+    let errors = errors.lock().unwrap();
+    assert_eq!(errors.len(), 1);
+    assert!(
+        errors[0].starts_with("Failed to parse command"),
+        "{:?}",
+        errors
+    );
+
+    let prompts: Vec<_> = reads
+        .lock()
+        .unwrap()
+        .iter()
+        .map(|read| read.0.clone())
+        .collect();
+    assert_eq!(prompts, vec![Prompt::new("db> "); 3]);
+
+    // The line starting with a space isn't in the history
+    assert_eq!(
+        std::fs::read_to_string(&history_file).unwrap(),
+        "earlier\nnamed_args 1 2\n"
+    );
+}
+
+#[test]
+fn retry_failed_line() {
+    let (source, reads) = ReadsSource::new(&["named_args 1 x", "quote"]);
+    let mut repl = Repl::builder()
+        .line_source(source)
+        .retry_failed_line(true)
+        .build()
+        .unwrap();
+
+    assert_eq!(
+        repl.read_command::<Command>().unwrap(),
+        ReadOutcome::Command(Command::Quote)
+    );
+    assert_eq!(
+        repl.read_command::<Command>().unwrap(),
+        ReadOutcome::Command(Command::Quit)
+    );
+    // Only the line after the failed one starts with it
+    let initials: Vec<_> = reads
+        .lock()
+        .unwrap()
+        .iter()
+        .map(|read| read.1.clone())
+        .collect();
+    assert_eq!(
+        initials,
+        vec![None, Some("named_args 1 x".to_string()), None]
+    );
+}
+
+#[test]
+fn record_and_replay() {
+    let tmp = tempfile::tempdir().unwrap();
+    let dir = tmp.path();
+    let path = dir.join("session.rec");
+    let script = dir.join("script.repl");
+    std::fs::write(&script, "named_args 3 4\n").unwrap();
+
+    let builder = Repl::builder().record(&path);
+    let mut repl =
+        replman::testing::ScriptedRepl::with_builder(builder).unwrap();
+    repl.feed("set x 1")
+        .feed("unnamed_args $x 2")
+        .feed("bogus")
+        .feed(format!("source {}", script.display()));
+
+    let printer = repl.printer();
+    repl.run(&mut |cmd: Command| {
+        printer.println(format!("got {:?}", cmd))?;
+        match cmd {
+            Command::Quit => Ok(ControlFlow::Exit),
+            _ => Ok(ControlFlow::Continue),
+        }
+    })
+    .unwrap();
+    repl.stop_recording();
+
+    let recording = std::fs::read_to_string(&path).unwrap();
+    let records: Vec<_> = recording
+        .lines()
+        .map(|line| line.split_once(' ').unwrap().1)
+        .collect();
+    assert_eq!(
+        records,
+        vec![
+            "in set x 1",
+            "in unnamed_args $x 2",
+            "cmd UnnamedArgs(1, 2)",
+            "out got UnnamedArgs(1, 2)",
+            "in bogus",
+            "err Failed to parse command: unrecognized command 'bogus'",
+            &format!("in source {}", script.display()),
+            "in named_args 3 4",
+            "cmd NamedArgs { left: 3, right: 4 }",
+            "out got NamedArgs { left: 3, right: 4 }",
+            "cmd Quit",
+            "out got Quit",
+        ]
+    );
+
+    let mut replayed = vec![];
+    Repl::from_reader(std::io::empty(), "empty")
+        .replay(&path, &mut |cmd: Command| {
+            replayed.push(cmd);
+            Ok(ControlFlow::Continue)
+        })
+        .unwrap();
+    // The sourced line is replayed once, from the recording
+    assert_eq!(
+        replayed,
+        vec![
+            Command::UnnamedArgs(1, 2),
+            Command::NamedArgs { left: 3, right: 4 },
+        ]
+    );
+}
+
+#[derive(ReplCmd)]
+enum WithoutDebug {
+    /// Pings
+    Ping,
+}
+
+#[test]
+fn record_without_debug() {
+    let tmp = tempfile::tempdir().unwrap();
+    let path = tmp.path().join("session.rec");
+
+    let builder = Repl::builder().record(&path);
+    let mut repl =
+        replman::testing::ScriptedRepl::with_builder(builder).unwrap();
+    repl.feed("Ping");
+
+    let mut pings = 0;
+    repl.run(&mut |WithoutDebug::Ping| {
+        pings += 1;
+        Ok(ControlFlow::Continue)
+    })
+    .unwrap();
+    repl.stop_recording();
+
+    assert_eq!(pings, 1);
+    let recording = std::fs::read_to_string(&path).unwrap();
+    let records: Vec<_> = recording
+        .lines()
+        .map(|line| line.split_once(' ').unwrap().1)
+        .collect();
+    assert_eq!(records, vec!["in Ping"]);
+}
+
+#[derive(PartialEq, Debug, ReplCmd)]
+#[replman(rename_all = "snake_case")]
+enum Shadowing {
+    Set {
+        key: String,
+        value: String,
+    },
+    Source(String),
+    History,
+    #[replman(starts_with = "al")]
+    Alert,
+}
+
+#[test]
+fn commands_shadow_builtins() {
+    let source = ScriptedSource::new(["set key value", "source foo.repl"]);
+    source.push_line("history");
+    source.push_line("alias");
+    let mut repl = Repl::builder().line_source(source).build().unwrap();
+
+    assert_eq!(
+        repl.read_command::<Shadowing>().unwrap(),
+        ReadOutcome::Command(Shadowing::Set {
+            key: "key".to_string(),
+            value: "value".to_string()
+        })
+    );
+    assert_eq!(
+        repl.read_command::<Shadowing>().unwrap(),
+        ReadOutcome::Command(Shadowing::Source("foo.repl".to_string()))
+    );
+    assert_eq!(
+        repl.read_command::<Shadowing>().unwrap(),
+        ReadOutcome::Command(Shadowing::History)
+    );
+    // Matches the `starts_with` prefix
+    assert_eq!(
+        repl.read_command::<Shadowing>().unwrap(),
+        ReadOutcome::Command(Shadowing::Alert)
+    );
+
+    let help = repl.help::<Shadowing>();
+    assert!(!help.contains("Sets a variable"), "{}", help);
+    assert!(!help.contains("Runs the commands in a file"), "{}", help);
+    assert!(!help.contains("Defines an alias"), "{}", help);
+    assert!(help.contains("Removes an alias"), "{}", help);
+}
+
+#[test]
+fn raw_commands_are_not_redirected() {
+    let tmp = tempfile::tempdir().unwrap();
+    let dir = tmp.path();
+    let file = dir.join("out.txt");
+
+    let source = ScriptedSource::new(["sql SELECT * FROM t WHERE x > 1"]);
+    source.push_line(format!("named_args 1 2 > {}", file.display()));
+    let mut repl = Repl::builder()
+        .line_source(source)
+        .pipes(true)
+        .build()
+        .unwrap();
+
+    let (cmd, output) = repl
+        .read_command_with_output::<Command>()
+        .unwrap()
+        .command()
+        .unwrap();
+    assert_eq!(
+        cmd,
+        Command::Sql {
+            query: "SELECT * FROM t WHERE x > 1".to_string()
+        }
+    );
+    output.finish().unwrap();
+    assert!(!dir.join("1").exists());
+
+    let (cmd, mut output) = repl
+        .read_command_with_output::<Command>()
+        .unwrap()
+        .command()
+        .unwrap();
+    assert_eq!(cmd, Command::NamedArgs { left: 1, right: 2 });
+    std::io::Write::write_all(&mut output, b"redirected").unwrap();
+    output.finish().unwrap();
+    assert_eq!(std::fs::read_to_string(&file).unwrap(), "redirected");
+}
+
+#[test]
+fn run_rejects_redirected_commands() {
+    let tmp = tempfile::tempdir().unwrap();
+    let file = tmp.path().join("variables.txt");
+
+    let builder = Repl::builder().pipes(true);
+    let mut repl =
+        replman::testing::ScriptedRepl::with_builder(builder).unwrap();
+    repl.feed("set greeting hello")
+        .feed(format!("set > {}", file.display()))
+        .feed("named_args 1 2 | head -1")
+        .feed("named_args 3 4");
+
+    let mut handled = vec![];
+    repl.run(&mut |cmd: Command| {
+        let flow = match cmd {
+            Command::Quit => ControlFlow::Exit,
+            _ => ControlFlow::Continue,
+        };
+        handled.push(cmd);
+        Ok(flow)
+    })
+    .unwrap();
+
+    assert_eq!(
+        handled,
+        vec![Command::NamedArgs { left: 3, right: 4 }, Command::Quit]
+    );
+    assert_eq!(
+        repl.take_errors(),
+        vec!["The output of commands can't be redirected in this repl"]
+    );
+    // Builtins are still redirected
+    assert!(std::fs::read_to_string(&file).unwrap().contains("hello"));
+    assert!(!repl.take_output().contains("hello"));
+}
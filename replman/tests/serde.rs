@@ -0,0 +1,145 @@
+#![cfg(feature = "serde")]
+
+use std::path::PathBuf;
+
+use proptest::prelude::*;
+use replman::prelude::*;
+use replman::ReplCmdParse;
+
+#[derive(Debug, Clone, PartialEq, ReplCmd)]
+#[replman(rename_all = "kebab-case", display)]
+enum Command {
+    Quit,
+    Move {
+        x: i64,
+        y: f64,
+    },
+    Say(String),
+    Tag {
+        name: String,
+        value: Option<u32>,
+    },
+    Flag(bool, char),
+    Open {
+        path: PathBuf,
+    },
+    Log {
+        #[replman(serde)]
+        level: Level,
+    },
+    #[replman(raw)]
+    Eval(String),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, serde::Serialize)]
+#[serde(rename_all = "lowercase")]
+enum Level {
+    Debug,
+    Info,
+}
+
+impl ReplCmdParse for Level {
+    fn parse(item: Option<&str>) -> anyhow::Result<Self> {
+        match item {
+            Some("debug") => Ok(Self::Debug),
+            Some("info") => Ok(Self::Info),
+            Some(level) => anyhow::bail!("Unknown level '{}'", level),
+            None => anyhow::bail!("Missing level"),
+        }
+    }
+
+    fn parse_default(s: &str) -> anyhow::Result<Self> {
+        Self::parse(Some(s))
+    }
+}
+
+fn arg() -> impl Strategy<Value = String> {
+    any::<String>()
+}
+
+/// Whether `s` can't be written as a single token, which is read back the
+/// same by a repl expanding variables
+fn is_unquotable(s: &str) -> bool {
+    s.contains('\'') && (s.contains('"') || s.contains('$'))
+}
+
+fn command() -> impl Strategy<Value = Command> {
+    prop_oneof![
+        Just(Command::Quit),
+        (
+            any::<i64>(),
+            any::<f64>().prop_filter("NaN", |y| !y.is_nan())
+        )
+            .prop_map(|(x, y)| Command::Move { x, y }),
+        arg().prop_map(Command::Say),
+        (arg(), any::<Option<u32>>())
+            .prop_map(|(name, value)| Command::Tag { name, value }),
+        (any::<bool>(), any::<char>())
+            .prop_map(|(flag, c)| Command::Flag(flag, c)),
+        arg().prop_map(|path| Command::Open { path: path.into() }),
+        prop_oneof![Just(Level::Debug), Just(Level::Info)]
+            .prop_map(|level| Command::Log { level }),
+        "[^\\s].*".prop_map(Command::Eval),
+    ]
+}
+
+proptest! {
+    #[test]
+    fn round_trip(cmd in command()) {
+        match cmd.try_to_command_line() {
+            Ok(line) => {
+                prop_assert_eq!(Command::parse_str(&line).unwrap(), cmd, "{}", line);
+            }
+            Err(_) => {
+                let arg = match &cmd {
+                    Command::Say(arg) | Command::Tag { name: arg, .. } => arg.clone(),
+                    Command::Open { path } => path.display().to_string(),
+                    _ => String::new(),
+                };
+                prop_assert!(is_unquotable(&arg), "{:?}", cmd);
+            }
+        }
+    }
+}
+
+#[test]
+fn command_lines() {
+    let cmd = Command::Tag {
+        name: "a b".to_string(),
+        value: None,
+    };
+    assert_eq!(cmd.try_to_command_line().unwrap(), "tag 'a b'");
+
+    let cmd = Command::Move { x: -1, y: 0.5 };
+    assert_eq!(cmd.try_to_command_line().unwrap(), "move -1 0.5");
+    assert_eq!(Command::Quit.try_to_command_line().unwrap(), "quit");
+
+    let cmd = Command::Log { level: Level::Info };
+    assert_eq!(cmd.to_string(), "log info");
+
+    let cmd = Command::Say("it's \"x\"".to_string());
+    assert!(cmd.try_to_command_line().is_err());
+    assert_eq!(cmd.to_command_line(), "say 'it's \"x\"'");
+}
+
+#[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+struct Config {
+    #[serde(with = "replman::serde")]
+    on_start: Command,
+}
+
+#[test]
+fn serde_helpers() {
+    let config = Config {
+        on_start: Command::Say("it's".to_string()),
+    };
+
+    let json = serde_json::to_string(&config).unwrap();
+    assert_eq!(json, r#"{"on_start":"say \"it's\""}"#);
+    assert_eq!(serde_json::from_str::<Config>(&json).unwrap(), config);
+
+    let err = serde_json::from_str::<Config>(r#"{"on_start":"sya hi"}"#)
+        .unwrap_err()
+        .to_string();
+    assert!(err.contains("unrecognized command 'sya'"), "{}", err);
+}
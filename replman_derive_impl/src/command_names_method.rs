@@ -0,0 +1,27 @@
+use syn::{parse_quote, DataEnum, LitStr};
+
+use crate::common::effective_variant_name;
+use crate::enum_attributes::EnumAttributes;
+use crate::variant_attributes::VariantAttributes;
+
+pub fn derive_command_names_method(
+    input: &DataEnum,
+    attrs: &EnumAttributes,
+) -> syn::ImplItemMethod {
+    let mut names: Vec<LitStr> = vec![];
+
+    for variant in &input.variants {
+        let variant_attributes = VariantAttributes::extract(&variant.attrs);
+        let effective_variant_name =
+            effective_variant_name(variant, attrs, &variant_attributes);
+
+        names.push(effective_variant_name.main_name);
+        names.extend(effective_variant_name.aliases);
+    }
+
+    parse_quote! {
+        fn command_names() -> &'static [&'static str] {
+            &[#(#names),*]
+        }
+    }
+}
@@ -0,0 +1,86 @@
+use quote::quote;
+use syn::{parse_quote, DataEnum, GenericArgument, PathArguments, Type};
+
+use crate::common::effective_variant_name;
+use crate::enum_attributes::EnumAttributes;
+use crate::field_attributes::{FieldAttributes, FieldDefault};
+use crate::variant_attributes::VariantAttributes;
+
+pub fn derive_commands_method(
+    input: &DataEnum,
+    attrs: &EnumAttributes,
+) -> syn::ImplItemMethod {
+    let commands = input.variants.iter().map(|variant| {
+        let variant_attributes = VariantAttributes::extract(&variant.attrs);
+        let effective_variant_name =
+            effective_variant_name(variant, attrs, &variant_attributes);
+
+        let name = &effective_variant_name.main_name;
+        let aliases = &effective_variant_name.aliases;
+        let starts_with = &effective_variant_name.start_withs;
+
+        let args = variant.fields.iter().enumerate().map(|(idx, field)| {
+            let name = match &field.ident {
+                Some(ident) => ident.to_string(),
+                None => idx.to_string(),
+            };
+
+            let (ty, is_option) = match option_inner_type(&field.ty) {
+                Some(inner) => (inner, true),
+                None => (&field.ty, false),
+            };
+            let ty = quote!(#ty).to_string().replace(' ', "");
+
+            let has_default = !matches!(
+                FieldAttributes::extract(&field.attrs).default,
+                FieldDefault::None
+            );
+            let optional = is_option || has_default;
+            let raw = variant_attributes.raw;
+
+            quote! {
+                ::replman::ArgInfo {
+                    name: #name,
+                    ty: #ty,
+                    optional: #optional,
+                    raw: #raw,
+                }
+            }
+        });
+
+        quote! {
+            ::replman::CommandInfo {
+                name: #name,
+                aliases: &[#(#aliases),*],
+                starts_with: &[#(#starts_with),*],
+                args: &[#(#args),*],
+            }
+        }
+    });
+
+    parse_quote! {
+        fn commands() -> &'static [::replman::CommandInfo] {
+            &[#(#commands),*]
+        }
+    }
+}
+
+/// `T` of an `Option<T>`
+fn option_inner_type(ty: &Type) -> Option<&Type> {
+    let segment = match ty {
+        Type::Path(path) => path.path.segments.last()?,
+        _ => return None,
+    };
+
+    if segment.ident != "Option" {
+        return None;
+    }
+
+    match &segment.arguments {
+        PathArguments::AngleBracketed(args) => match args.args.first()? {
+            GenericArgument::Type(inner) => Some(inner),
+            _ => None,
+        },
+        _ => None,
+    }
+}
@@ -0,0 +1,15 @@
+use syn::parse_quote;
+
+/// Formats the command with `Debug`, if the enum implements it
+pub fn derive_debug_record_method() -> syn::ImplItemMethod {
+    parse_quote! {
+        fn debug_record(&self) -> Option<String> {
+            #[allow(unused_imports)]
+            use ::replman::command_line::{
+                FormatRecord as _, FormatRecordFallback as _,
+            };
+
+            (&::replman::command_line::Record(self)).format_record()
+        }
+    }
+}
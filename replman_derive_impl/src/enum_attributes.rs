@@ -4,6 +4,8 @@ use syn::{Attribute, Lit};
 #[derive(Debug, Default)]
 pub struct EnumAttributes {
     pub rename_all: Option<Case>,
+    /// Derives `Display` with the command line
+    pub display: bool,
 }
 
 impl EnumAttributes {
@@ -35,7 +37,7 @@ fn iter_over_attrs(input_attrs: &[Attribute], ret: &mut EnumAttributes) {
             match meta {
                 syn::Meta::List(meta_list) => {
                     for nested in &meta_list.nested {
-                        try_extract_rename_all(nested, ret);
+                        try_extract(nested, ret);
                     }
                 }
                 _ => panic!("Invalid first level meta"),
@@ -44,8 +46,13 @@ fn iter_over_attrs(input_attrs: &[Attribute], ret: &mut EnumAttributes) {
     }
 }
 
-fn try_extract_rename_all(nested: &syn::NestedMeta, ret: &mut EnumAttributes) {
+fn try_extract(nested: &syn::NestedMeta, ret: &mut EnumAttributes) {
     match nested {
+        syn::NestedMeta::Meta(syn::Meta::Path(path))
+            if path == &syn::parse_quote!(display) =>
+        {
+            ret.display = true;
+        }
         syn::NestedMeta::Meta(syn::Meta::NameValue(meta_name_value)) => {
             if meta_name_value.path == syn::parse_quote!(rename_all) {
                 if let Lit::Str(lit_str) = &meta_name_value.lit {
@@ -3,21 +3,18 @@ use syn::{parse_quote, Attribute, Lit};
 #[derive(Debug, Clone, Default)]
 pub struct FieldAttributes {
     pub default: FieldDefault,
+    /// Written with its `Serialize` impl by `ToCommandLine`
+    pub serde: bool,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default)]
 pub enum FieldDefault {
+    #[default]
     None,
     Some(String),
     Default,
 }
 
-impl Default for FieldDefault {
-    fn default() -> Self {
-        Self::None
-    }
-}
-
 impl FieldAttributes {
     pub fn extract(attrs: &[Attribute]) -> Self {
         let mut ret = Self::default();
@@ -51,6 +48,8 @@ fn try_extract_default(nested: &syn::NestedMeta, ret: &mut FieldAttributes) {
             syn::Meta::Path(meta_path) => {
                 if meta_path == &parse_quote!(default) {
                     ret.default = FieldDefault::Default;
+                } else if meta_path == &parse_quote!(serde) {
+                    ret.serde = true;
                 }
             }
             syn::Meta::NameValue(name_value) => {
@@ -26,6 +26,18 @@ pub fn derive_help_method(
         write!(&mut help_line, "{}", variant_name_str);
 
         match &variant.fields {
+            syn::Fields::Named(named_args) if variant_attributes.raw => {
+                for arg in &named_args.named {
+                    write!(
+                        &mut help_line,
+                        " <{}...>",
+                        arg.ident.as_ref().unwrap()
+                    );
+                }
+            }
+            syn::Fields::Unnamed(_) if variant_attributes.raw => {
+                write!(&mut help_line, " <0...>");
+            }
             syn::Fields::Named(named_args) => {
                 for arg in &named_args.named {
                     write!(
@@ -49,7 +61,7 @@ pub fn derive_help_method(
             let alignment = help_line.len();
             let alignment: String = (0..alignment).map(|_| ' ').collect();
 
-            write!(&mut help_line, "{}", &doc_lines[0].value());
+            write!(&mut help_line, "{}", doc_lines[0].value());
 
             for doc_line in doc_lines[1..].iter() {
                 writeln!(&mut help_line);
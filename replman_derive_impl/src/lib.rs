@@ -1,16 +1,26 @@
+use command_names_method::derive_command_names_method;
+use commands_method::derive_commands_method;
+use debug_record_method::derive_debug_record_method;
 use help_method::derive_help_method;
+use on_eof_method::derive_on_eof_method;
 use parse_method::derive_parse_method;
 use proc_macro2::TokenStream;
 use quote::quote;
 use syn::DeriveInput;
+use to_command_line_impl::derive_to_command_line_impl;
 
 use crate::enum_attributes::EnumAttributes;
 
+mod command_names_method;
+mod commands_method;
 mod common;
+mod debug_record_method;
 mod enum_attributes;
 mod field_attributes;
 mod help_method;
+mod on_eof_method;
 mod parse_method;
+mod to_command_line_impl;
 mod variant_attributes;
 
 pub fn derive_repl_cmd_impl(input: DeriveInput) -> TokenStream {
@@ -25,12 +35,24 @@ pub fn derive_repl_cmd_impl(input: DeriveInput) -> TokenStream {
 
     let help_impl = derive_help_method(data_enum, &attrs);
     let parse_impl = derive_parse_method(data_enum, &attrs);
+    let command_names_impl = derive_command_names_method(data_enum, &attrs);
+    let on_eof_impl = derive_on_eof_method(data_enum);
+    let commands_impl = derive_commands_method(data_enum, &attrs);
+    let debug_record_impl = derive_debug_record_method();
+    let to_command_line_impl =
+        derive_to_command_line_impl(ty, data_enum, &attrs);
 
     let output = quote! {
         impl ReplCmd for #ty {
             #help_impl
             #parse_impl
+            #command_names_impl
+            #on_eof_impl
+            #commands_impl
+            #debug_record_impl
         }
+
+        #to_command_line_impl
     };
 
     output
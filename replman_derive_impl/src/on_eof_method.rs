@@ -0,0 +1,28 @@
+use syn::{parse_quote, DataEnum};
+
+use crate::variant_attributes::VariantAttributes;
+
+pub fn derive_on_eof_method(input: &DataEnum) -> Option<syn::ImplItemMethod> {
+    let mut on_eof_variants = input
+        .variants
+        .iter()
+        .filter(|variant| VariantAttributes::extract(&variant.attrs).on_eof);
+
+    let variant = on_eof_variants.next()?;
+
+    if on_eof_variants.next().is_some() {
+        panic!("Only one variant can be marked with on_eof");
+    }
+
+    if !matches!(variant.fields, syn::Fields::Unit) {
+        panic!("The on_eof variant can't have fields");
+    }
+
+    let variant_name = &variant.ident;
+
+    Some(parse_quote! {
+        fn on_eof() -> Option<Self> {
+            Some(Self::#variant_name)
+        }
+    })
+}
@@ -17,14 +17,17 @@ pub fn derive_parse_method(
     } = variant_matches(data_enum, attrs);
 
     let parse_impl: syn::ImplItemMethod = syn::parse_quote! {
-        fn parse<'a, I>(mut parts: I) -> anyhow::Result<Self>
+        #[allow(unused_variables)]
+        fn parse<I>(line: &str, mut parts: I) -> anyhow::Result<Self>
         where
             Self: Sized,
-            I: Iterator<Item = anyhow::Result<&'a str>> + 'a
+            I: Iterator<Item = anyhow::Result<String>>
         {
-            let cmd_word = parts.next().unwrap()?;
+            let cmd_word = parts
+                .next()
+                .ok_or_else(|| anyhow::anyhow!("missing command"))??;
 
-            match cmd_word {
+            match cmd_word.as_str() {
                 #(#exact_matches)*
                 #(#aliases)*
                 #(#start_with_matches)*
@@ -58,6 +61,7 @@ fn variant_matches(
         let main_name = &effective_variant_name.main_name;
 
         let variant_body: syn::Expr = match &variant.fields {
+            _ if variant_attributes.raw => raw_variant_body(variant),
             Fields::Named(named) => {
                 let field_parses = named.named.iter().map(|field| {
                     let ident = field.ident.as_ref().unwrap();
@@ -66,14 +70,14 @@ fn variant_matches(
 
                     match field_attributes.default {
                         field_attributes::FieldDefault::None => quote! {
-                            #ident: ::replman::ReplCmdParse::parse(parts.next().transpose()?)?,
+                            #ident: ::replman::ReplCmdParse::parse(parts.next().transpose()?.as_deref())?,
                         },
                         field_attributes::FieldDefault::Some(default_value) => quote! {
-                            #ident: ::replman::ReplCmdParse::parse_default(parts.next().transpose()?.unwrap_or(#default_value))?,
+                            #ident: ::replman::ReplCmdParse::parse_default(parts.next().transpose()?.as_deref().unwrap_or(#default_value))?,
                         },
                         field_attributes::FieldDefault::Default => quote! {
                             #ident: match parts.next() {
-                                Some(s) => ::replman::ReplCmdParse::parse_default(s?)?,
+                                Some(s) => ::replman::ReplCmdParse::parse_default(&s?)?,
                                 None => Default::default(),
                             },
                         },
@@ -92,7 +96,7 @@ fn variant_matches(
             Fields::Unnamed(unnamed) => {
                 let field_parses = unnamed.unnamed.iter().map(|_| {
                     quote! {
-                        ::replman::ReplCmdParse::parse(parts.next().transpose()?)?,
+                        ::replman::ReplCmdParse::parse(parts.next().transpose()?.as_deref())?,
                     }
                 });
 
@@ -123,3 +127,24 @@ fn variant_matches(
 
     variant_matches
 }
+
+fn raw_variant_body(variant: &syn::Variant) -> syn::Expr {
+    let variant_name = &variant.ident;
+
+    if variant.fields.len() != 1 {
+        panic!("A raw variant must have exactly one field");
+    }
+
+    match &variant.fields {
+        Fields::Named(named) => {
+            let ident = named.named[0].ident.as_ref().unwrap();
+
+            parse_quote! {
+                { Ok(Self::#variant_name { #ident: ::replman::line_remainder(line).to_string() }) }
+            }
+        }
+        _ => parse_quote! {
+            { Ok(Self::#variant_name(::replman::line_remainder(line).to_string())) }
+        },
+    }
+}
@@ -0,0 +1,138 @@
+use proc_macro2::{Ident, Span, TokenStream};
+use quote::quote;
+use syn::{DataEnum, Field, Fields};
+
+use crate::common::effective_variant_name;
+use crate::enum_attributes::EnumAttributes;
+use crate::field_attributes::{FieldAttributes, FieldDefault};
+use crate::variant_attributes::VariantAttributes;
+
+pub fn derive_to_command_line_impl(
+    ty: &Ident,
+    data_enum: &DataEnum,
+    attrs: &EnumAttributes,
+) -> TokenStream {
+    let arms = data_enum.variants.iter().map(|variant| {
+        let variant_name = &variant.ident;
+        let variant_attributes = VariantAttributes::extract(&variant.attrs);
+        let main_name =
+            effective_variant_name(variant, attrs, &variant_attributes)
+                .main_name;
+
+        let bindings: Vec<_> = variant
+            .fields
+            .iter()
+            .enumerate()
+            .map(|(idx, field)| match &field.ident {
+                Some(ident) => ident.clone(),
+                None => {
+                    Ident::new(&format!("field_{}", idx), Span::call_site())
+                }
+            })
+            .collect();
+
+        let pattern = match &variant.fields {
+            Fields::Named(_) => quote!(Self::#variant_name { #(#bindings),* }),
+            Fields::Unnamed(_) => quote!(Self::#variant_name(#(#bindings),*)),
+            Fields::Unit => quote!(Self::#variant_name),
+        };
+
+        let body = if variant_attributes.raw {
+            let remainder = &bindings[0];
+            quote!(::replman::command_line::write_raw(#main_name, #remainder))
+        } else {
+            let args = variant.fields.iter().zip(&bindings).enumerate().map(
+                |(idx, (field, binding))| {
+                    let name = match &field.ident {
+                        Some(ident) => ident.to_string(),
+                        None => idx.to_string(),
+                    };
+                    let arg = field_arg(field, binding);
+                    let is_default =
+                        field_is_default(&variant.fields, field, binding);
+
+                    quote!((#name, #arg, #is_default))
+                },
+            );
+
+            quote! {
+                ::replman::command_line::write_line(
+                    #main_name,
+                    vec![#(#args),*],
+                )
+            }
+        };
+
+        quote!(#pattern => #body,)
+    });
+
+    let display_impl = attrs.display.then(|| {
+        quote! {
+            impl ::std::fmt::Display for #ty {
+                fn fmt(&self, f: &mut ::std::fmt::Formatter<'_>) -> ::std::fmt::Result {
+                    f.write_str(&::replman::ToCommandLine::to_command_line(self))
+                }
+            }
+        }
+    });
+
+    quote! {
+        impl ::replman::ToCommandLine for #ty {
+            fn write_command_line(&self) -> ::replman::WrittenLine {
+                #[allow(unused_imports)]
+                use ::replman::command_line::{
+                    IsDefault as _, IsDefaultFallback as _, WriteArg as _,
+                    WriteArgFallback as _,
+                };
+
+                match self {
+                    #(#arms)*
+                }
+            }
+        }
+
+        #display_impl
+    }
+}
+
+/// The text of an argument, `None` if it's left out
+fn field_arg(field: &Field, binding: &Ident) -> TokenStream {
+    if FieldAttributes::extract(&field.attrs).serde {
+        quote!(::replman::serde::to_arg(#binding))
+    } else {
+        quote!((&::replman::command_line::Arg(#binding)).write_arg())
+    }
+}
+
+/// Whether the argument is what the parser fills in when it's missing, only
+/// named fields have defaults
+fn field_is_default(
+    fields: &Fields,
+    field: &Field,
+    binding: &Ident,
+) -> TokenStream {
+    if !matches!(fields, Fields::Named(_)) {
+        return quote!(false);
+    }
+
+    let ty = &field.ty;
+    match FieldAttributes::extract(&field.attrs).default {
+        FieldDefault::None => quote!(false),
+        FieldDefault::Some(default_value) => quote! {
+            match <#ty as ::replman::ReplCmdParse>::parse_default(#default_value) {
+                Ok(default) => {
+                    (&::replman::command_line::Defaulted(#binding, default))
+                        .is_default()
+                }
+                Err(_) => false,
+            }
+        },
+        FieldDefault::Default => quote! {
+            (&::replman::command_line::Defaulted(
+                #binding,
+                <#ty as ::std::default::Default>::default(),
+            ))
+            .is_default()
+        },
+    }
+}
@@ -5,6 +5,10 @@ pub struct VariantAttributes {
     pub aliases: Vec<LitStr>,
     pub doc_lines: Vec<LitStr>,
     pub starts_withs: Vec<LitStr>,
+    /// The single field receives the untouched remainder of the line
+    pub raw: bool,
+    /// Returned instead of the end of input, e.g. on Ctrl-D
+    pub on_eof: bool,
 }
 
 impl VariantAttributes {
@@ -55,6 +59,14 @@ fn extract_doc_line(attr: &Attribute, ret: &mut Vec<LitStr>) {
 
 fn extract_replman(nested: &syn::NestedMeta, ret: &mut VariantAttributes) {
     match nested {
+        syn::NestedMeta::Meta(syn::Meta::Path(path)) => {
+            if path == &parse_quote!(raw) {
+                ret.raw = true;
+            }
+            if path == &parse_quote!(on_eof) {
+                ret.on_eof = true;
+            }
+        }
         syn::NestedMeta::Meta(syn::Meta::NameValue(name_value)) => {
             if name_value.path == parse_quote!(alias) {
                 if let Lit::Str(lit_str) = &name_value.lit {